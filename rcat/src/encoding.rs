@@ -0,0 +1,183 @@
+use std::io::{self, Read};
+
+use crate::CustomError;
+
+const CHUNK_SIZE: usize = 8192;
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+pub fn parse_encoding(name: &str) -> Result<Encoding, CustomError> {
+    match name.to_ascii_lowercase().replace('_', "-").as_str() {
+        "auto" => Ok(Encoding::Auto),
+        "utf-8" | "utf8" => Ok(Encoding::Utf8),
+        "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+        "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+        "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" => Ok(Encoding::Latin1),
+        _ => Err(CustomError::ErrUnknownEncoding),
+    }
+}
+
+/// Transcodes the wrapped reader from `encoding` to UTF-8
+pub struct Decoder<R: Read> {
+    inner: R,
+    encoding: Encoding,
+    is_start: bool,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R, encoding: Encoding) -> Decoder<R> {
+        Decoder {
+            inner,
+            encoding,
+            is_start: true,
+            input: vec![],
+            output: vec![],
+            position: 0,
+        }
+    }
+
+    fn fill_input(&mut self, min_len: usize) -> io::Result<bool> {
+        let mut chunk = [0; CHUNK_SIZE];
+
+        while self.input.len() < min_len {
+            let count = self.inner.read(&mut chunk)?;
+            if count == 0 {
+                return Ok(false);
+            }
+            self.input.extend_from_slice(&chunk[..count]);
+        }
+
+        Ok(true)
+    }
+
+    fn strip_bom(&mut self) -> io::Result<()> {
+        self.fill_input(UTF8_BOM.len())?;
+
+        if self.encoding == Encoding::Auto {
+            self.encoding = sniff_encoding(&self.input);
+        }
+
+        let bom: &[u8] = match self.encoding {
+            Encoding::Utf8 => &UTF8_BOM,
+            Encoding::Utf16Le => &UTF16LE_BOM,
+            Encoding::Utf16Be => &UTF16BE_BOM,
+            _ => &[],
+        };
+
+        if self.input.starts_with(bom) {
+            self.input.drain(..bom.len());
+        }
+
+        Ok(())
+    }
+
+    fn decode_chunk(&mut self) -> io::Result<()> {
+        let is_eof = !self.fill_input(self.input.len() + 1)?;
+
+        let consumed = match self.encoding {
+            Encoding::Utf16Le => {
+                decode_utf16(&self.input, &mut self.output, is_eof, u16::from_le_bytes)?
+            }
+            Encoding::Utf16Be => {
+                decode_utf16(&self.input, &mut self.output, is_eof, u16::from_be_bytes)?
+            }
+            Encoding::Latin1 => {
+                let decoded: String = self.input.iter().map(|&byte| byte as char).collect();
+                self.output.extend_from_slice(decoded.as_bytes());
+                self.input.len()
+            }
+            _ => {
+                self.output.extend_from_slice(&self.input);
+                self.input.len()
+            }
+        };
+
+        self.input.drain(..consumed);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_start {
+            self.strip_bom()?;
+            self.is_start = false;
+        }
+
+        while self.position == self.output.len() {
+            self.output.clear();
+            self.position = 0;
+
+            self.decode_chunk()?;
+            if self.output.is_empty() && self.input.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let count = buf.len().min(self.output.len() - self.position);
+        buf[..count].copy_from_slice(&self.output[self.position..self.position + count]);
+        self.position += count;
+
+        Ok(count)
+    }
+}
+
+fn sniff_encoding(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&UTF16LE_BOM) {
+        return Encoding::Utf16Le;
+    }
+
+    if bytes.starts_with(&UTF16BE_BOM) {
+        return Encoding::Utf16Be;
+    }
+
+    Encoding::Utf8
+}
+
+/// Decodes as many complete UTF-16 code units as possible and returns the number of bytes consumed
+fn decode_utf16(
+    input: &[u8],
+    output: &mut Vec<u8>,
+    is_eof: bool,
+    to_unit: fn([u8; 2]) -> u16,
+) -> io::Result<usize> {
+    let mut units: Vec<u16> = input
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]))
+        .collect();
+
+    // Keep A Trailing High Surrogate Until Its Pair Arrives
+    if !is_eof && matches!(units.last(), Some(0xD800..=0xDBFF)) {
+        units.pop();
+    }
+
+    if is_eof && !input.len().is_multiple_of(2) {
+        return Err(undecodable());
+    }
+
+    for character in char::decode_utf16(units.iter().copied()) {
+        let character = character.map_err(|_| undecodable())?;
+        let mut buf = [0; 4];
+        output.extend_from_slice(character.encode_utf8(&mut buf).as_bytes());
+    }
+
+    Ok(units.len() * 2)
+}
+
+fn undecodable() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "undecodable input")
+}
@@ -0,0 +1,368 @@
+use std::path::Path;
+
+use crate::CustomError;
+
+const RESET: &str = "\x1b[0m";
+const GUTTER: &str = "\x1b[2m";
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const COMMENT: &str = "\x1b[90m";
+
+#[derive(Debug, PartialEq)]
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    string_delimiters: &'static [char],
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "rust",
+        extensions: &["rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"'],
+    },
+    Language {
+        name: "python",
+        extensions: &["py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        string_delimiters: &['"', '\''],
+    },
+    Language {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs", "ts", "jsx", "tsx"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "let",
+            "new",
+            "null",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\'', '`'],
+    },
+    Language {
+        name: "c",
+        extensions: &["c", "h", "cc", "cpp", "hpp"],
+        keywords: &[
+            "auto",
+            "break",
+            "case",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "float",
+            "for",
+            "goto",
+            "if",
+            "int",
+            "long",
+            "namespace",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "typedef",
+            "union",
+            "unsigned",
+            "void",
+            "volatile",
+            "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\''],
+    },
+    Language {
+        name: "go",
+        extensions: &["go"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\'', '`'],
+    },
+    Language {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        string_delimiters: &['"', '\''],
+    },
+    Language {
+        name: "toml",
+        extensions: &["toml"],
+        keywords: &["true", "false"],
+        line_comment: Some("#"),
+        block_comment: None,
+        string_delimiters: &['"', '\''],
+    },
+    Language {
+        name: "json",
+        extensions: &["json", "jsonl"],
+        keywords: &["true", "false", "null"],
+        line_comment: None,
+        block_comment: None,
+        string_delimiters: &['"'],
+    },
+];
+
+pub fn parse_language(name: &str) -> Result<&'static Language, CustomError> {
+    LANGUAGES
+        .iter()
+        .find(|language| language.name == name || language.extensions.contains(&name))
+        .ok_or(CustomError::ErrUnknownLanguage)
+}
+
+pub fn detect_language(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?;
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension))
+}
+
+pub fn parse_color(color: &str) -> Result<Color, CustomError> {
+    match color {
+        "auto" => Ok(Color::Auto),
+        "always" => Ok(Color::Always),
+        "never" => Ok(Color::Never),
+        _ => Err(CustomError::ErrInvalidColor),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Decides whether to color the output, honoring `NO_COLOR` in auto mode
+pub fn is_color_enabled(color: Color, is_tty: bool, no_color: Option<std::ffi::OsString>) -> bool {
+    match color {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => is_tty && no_color.is_none_or(|value| value.is_empty()),
+    }
+}
+
+pub fn paint_gutter(gutter: &str) -> String {
+    format!("{}{}{}", GUTTER, gutter, RESET)
+}
+
+/// Colors one line at a time, carrying block comments across lines
+pub struct Highlighter {
+    language: &'static Language,
+    is_in_block_comment: bool,
+}
+
+impl Highlighter {
+    pub fn new(language: &'static Language) -> Highlighter {
+        Highlighter {
+            language,
+            is_in_block_comment: false,
+        }
+    }
+
+    pub fn highlight(&mut self, line_content: &str) -> String {
+        let mut result = String::with_capacity(line_content.len());
+        let mut rest = line_content;
+
+        if self.is_in_block_comment {
+            rest = self.close_block_comment(rest, 0, &mut result);
+        }
+
+        while let Some(character) = rest.chars().next() {
+            let language = self.language;
+
+            if let Some(line_comment) = language.line_comment {
+                if rest.starts_with(line_comment) {
+                    paint(&mut result, COMMENT, rest);
+                    break;
+                }
+            }
+
+            if let Some((block_start, _)) = language.block_comment {
+                if rest.starts_with(block_start) {
+                    self.is_in_block_comment = true;
+                    rest = self.close_block_comment(rest, block_start.len(), &mut result);
+                    continue;
+                }
+            }
+
+            let length = if language.string_delimiters.contains(&character) {
+                let length = string_length(rest, character);
+                paint(&mut result, STRING, &rest[..length]);
+                length
+            } else if character.is_ascii_digit() {
+                let length = word_length(rest);
+                paint(&mut result, NUMBER, &rest[..length]);
+                length
+            } else if character.is_alphabetic() || character == '_' {
+                let length = word_length(rest);
+                let word = &rest[..length];
+                match language.keywords.contains(&word) {
+                    true => paint(&mut result, KEYWORD, word),
+                    false => result.push_str(word),
+                }
+                length
+            } else {
+                result.push(character);
+                character.len_utf8()
+            };
+
+            rest = &rest[length..];
+        }
+
+        result
+    }
+
+    fn close_block_comment<'a>(
+        &mut self,
+        rest: &'a str,
+        offset: usize,
+        result: &mut String,
+    ) -> &'a str {
+        let (_, block_end) = self.language.block_comment.unwrap();
+
+        match rest[offset..].find(block_end) {
+            Some(index) => {
+                let end = offset + index + block_end.len();
+                paint(result, COMMENT, &rest[..end]);
+                self.is_in_block_comment = false;
+                &rest[end..]
+            }
+            None => {
+                paint(result, COMMENT, rest);
+                ""
+            }
+        }
+    }
+}
+
+fn paint(result: &mut String, color: &str, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    result.push_str(color);
+    result.push_str(text);
+    result.push_str(RESET);
+}
+
+fn word_length(text: &str) -> usize {
+    text.find(|character: char| !(character.is_alphanumeric() || character == '_'))
+        .unwrap_or(text.len())
+}
+
+/// Length of the string literal at the start of `text`, up to the closing delimiter or end of line
+fn string_length(text: &str, delimiter: char) -> usize {
+    let mut is_escaped = false;
+
+    for (index, character) in text.char_indices().skip(1) {
+        if is_escaped {
+            is_escaped = false;
+        } else if character == '\\' {
+            is_escaped = true;
+        } else if character == delimiter {
+            return index + character.len_utf8();
+        }
+    }
+
+    text.len()
+}
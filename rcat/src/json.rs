@@ -0,0 +1,126 @@
+use serde_json::{Deserializer, Value};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::{parse_custom_error, read_error, CustomError};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct JsonFormat {
+    pub is_compact: bool,
+    pub is_sorted: bool,
+}
+
+/// Reformats the documents in `reader` one at a time, so large inputs are never held in memory.
+/// Returns how many JSON lines records were broken, which are reported to `errors` as they come.
+pub fn write_documents<R: BufRead, W: Write, E: Write>(
+    reader: R,
+    path: &Path,
+    json_format: &JsonFormat,
+    writer: &mut W,
+    errors: &mut E,
+) -> Result<usize, CustomError> {
+    match is_json_lines(path) {
+        true => write_json_lines(reader, path, json_format, writer, errors),
+        false => write_json_stream(reader, path, json_format, writer).map(|_| 0),
+    }
+}
+
+fn is_json_lines(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("jsonl" | "ndjson")
+    )
+}
+
+/// One document per line, so a broken record is reported on its own and the records after it
+/// are still printed, which validates the whole file in one pass
+fn write_json_lines<R: BufRead, W: Write, E: Write>(
+    reader: R,
+    path: &Path,
+    json_format: &JsonFormat,
+    writer: &mut W,
+    errors: &mut E,
+) -> Result<usize, CustomError> {
+    let mut invalid = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(read_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<Value>(&line) {
+            Ok(document) => write_document(document, json_format, writer)?,
+            Err(_) => {
+                let error = CustomError::ErrInvalidJson(path.display().to_string(), index + 1);
+                writeln!(errors, "Error: {}", parse_custom_error(error))
+                    .map_err(|_| CustomError::ErrWriteFailed)?;
+                invalid += 1;
+            }
+        }
+    }
+
+    Ok(invalid)
+}
+
+/// Any whitespace separated sequence of documents, including ones spanning many lines
+fn write_json_stream<R: BufRead, W: Write>(
+    reader: R,
+    path: &Path,
+    json_format: &JsonFormat,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    let documents = Deserializer::from_reader(reader).into_iter::<Value>();
+
+    for document in documents {
+        let document = match document {
+            Ok(document) => document,
+            Err(error) if error.is_io() => return Err(CustomError::ErrUndecodableInput),
+            Err(error) => {
+                return Err(CustomError::ErrInvalidJson(
+                    path.display().to_string(),
+                    error.line(),
+                ))
+            }
+        };
+        write_document(document, json_format, writer)?;
+    }
+
+    Ok(())
+}
+
+fn write_document<W: Write>(
+    mut document: Value,
+    json_format: &JsonFormat,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    if json_format.is_sorted {
+        sort_keys(&mut document);
+    }
+
+    let result = match json_format.is_compact {
+        true => serde_json::to_writer(&mut *writer, &document),
+        false => serde_json::to_writer_pretty(&mut *writer, &document),
+    };
+
+    result
+        .ok()
+        .and_then(|_| writer.write_all(b"\n").ok())
+        .ok_or(CustomError::ErrWriteFailed)
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b));
+
+            for (key, mut entry) in entries {
+                sort_keys(&mut entry);
+                map.insert(key, entry);
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_keys),
+        _ => (),
+    }
+}
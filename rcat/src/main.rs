@@ -1,8 +1,16 @@
 extern crate atty;
 
 use clap::Parser;
-use std::io::{self, BufRead, BufReader};
-use std::path::PathBuf;
+use encoding::{Decoder, Encoding};
+use highlight::{Highlighter, Language};
+use json::JsonFormat;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+mod encoding;
+mod highlight;
+mod json;
 
 #[cfg(test)]
 mod tests;
@@ -20,33 +28,235 @@ struct Cli {
     /// Add line numbers only to non blank lines
     #[clap(short = 'b', action)]
     non_blank_line_numbers: bool,
+
+    /// Only print lines START:END (1-based, inclusive, negative values count from the end)
+    #[clap(long = "lines", allow_hyphen_values = true)]
+    lines: Option<String>,
+
+    /// Input encoding: auto, utf-8, utf-16le, utf-16be or iso-8859-1
+    #[clap(long = "from-encoding", default_value = "utf-8")]
+    from_encoding: String,
+
+    /// Convert CRLF line endings to LF
+    #[clap(long = "crlf-to-lf", action, conflicts_with = "lf-to-crlf")]
+    crlf_to_lf: bool,
+
+    /// Convert LF line endings to CRLF
+    #[clap(long = "lf-to-crlf", action)]
+    lf_to_crlf: bool,
+
+    /// Remove whitespace at the end of each line
+    #[clap(long = "strip-trailing-whitespace", action)]
+    strip_trailing_whitespace: bool,
+
+    /// Expand tabs to spaces with tab stops every N columns
+    #[clap(
+        long = "expand-tabs",
+        value_name = "N",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "8"
+    )]
+    expand_tabs: Option<usize>,
+
+    /// When to syntax-highlight the output: auto, always or never
+    #[clap(long = "color", default_value = "never")]
+    color: String,
+
+    /// Language to highlight as instead of detecting it from the file extension
+    #[clap(long = "language")]
+    language: Option<String>,
+
+    /// Pretty-print every JSON document or JSON line. Files ending in .jsonl or .ndjson are read
+    /// a record per line and every broken record is reported, while other files and stdin are
+    /// read as a stream of documents that stops at the first error
+    #[clap(
+        long = "json",
+        action,
+        conflicts_with_all = &[
+            "line-numbers",
+            "non-blank-line-numbers",
+            "lines",
+            "color",
+            "language",
+            "crlf-to-lf",
+            "lf-to-crlf",
+            "strip-trailing-whitespace",
+            "expand-tabs"
+        ]
+    )]
+    json: bool,
+
+    /// Print every JSON document on a single line instead
+    #[clap(long = "compact", action, requires = "json")]
+    compact: bool,
+
+    /// Sort the keys of every JSON object
+    #[clap(long = "sort-keys", action, requires = "json")]
+    sort_keys: bool,
 }
 
 #[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum CustomError {
     ErrNoInput,
     ErrInvalidPath,
+    ErrInvalidLineRange,
+    ErrUnknownEncoding,
+    ErrUndecodableInput,
+    ErrReadFailed(&'static str),
+    ErrNoTabSize,
+    ErrInvalidColor,
+    ErrUnknownLanguage,
+    ErrInvalidJson(String, usize),
+    ErrInvalidJsonRecords(usize),
+    ErrWriteFailed,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LineRange {
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl LineRange {
+    fn is_from_end(&self) -> bool {
+        matches!(self.start, Some(start) if start < 0)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Normalization {
+    crlf_to_lf: bool,
+    lf_to_crlf: bool,
+    strip_trailing_whitespace: bool,
+    expand_tabs: Option<usize>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Highlight {
+    is_enabled: bool,
+    language: Option<&'static Language>,
 }
 
 fn main() -> Result<(), CustomError> {
     let args = Cli::parse();
-    let paths = args.paths;
-    let line_numbers = args.line_numbers;
-    let non_blank_line_numbers = args.non_blank_line_numbers;
 
-    let result = process_input(paths, line_numbers, non_blank_line_numbers);
+    let result = process_args(args);
     match result {
-        Ok(result) => println!("{}", result),
+        Ok(result) => print!("{}", result),
         Err(error) => eprintln!("Error: {}", parse_custom_error(error)),
     }
 
     Ok(())
 }
 
+fn process_args(args: Cli) -> Result<String, CustomError> {
+    let line_range = match args.lines {
+        None => LineRange::default(),
+        Some(lines) => parse_line_range(&lines)?,
+    };
+
+    let encoding = encoding::parse_encoding(&args.from_encoding)?;
+
+    if args.json {
+        let json_format = JsonFormat {
+            is_compact: args.compact,
+            is_sorted: args.sort_keys,
+        };
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        process_json(
+            args.paths,
+            encoding,
+            &json_format,
+            &mut stdout,
+            &mut io::stderr(),
+        )?;
+        return Ok("".to_string());
+    }
+
+    let normalization = Normalization {
+        crlf_to_lf: args.crlf_to_lf,
+        lf_to_crlf: args.lf_to_crlf,
+        strip_trailing_whitespace: args.strip_trailing_whitespace,
+        expand_tabs: args.expand_tabs,
+    };
+
+    let color = highlight::parse_color(&args.color)?;
+    let highlight = Highlight {
+        is_enabled: highlight::is_color_enabled(
+            color,
+            atty::is(atty::Stream::Stdout),
+            std::env::var_os("NO_COLOR"),
+        ),
+        language: match args.language {
+            None => None,
+            Some(language) => Some(highlight::parse_language(&language)?),
+        },
+    };
+
+    process_input(
+        args.paths,
+        args.line_numbers,
+        args.non_blank_line_numbers,
+        &line_range,
+        encoding,
+        &normalization,
+        &highlight,
+    )
+}
+
 fn parse_custom_error(error: CustomError) -> String {
     match error {
         CustomError::ErrNoInput => "no input provided".to_string(),
         CustomError::ErrInvalidPath => "invalid filepath provided".to_string(),
+        CustomError::ErrInvalidLineRange => "invalid line range provided".to_string(),
+        CustomError::ErrUnknownEncoding => "unknown encoding provided".to_string(),
+        CustomError::ErrUndecodableInput => {
+            "input cannot be decoded with the given encoding".to_string()
+        }
+        CustomError::ErrReadFailed(reason) => format!("failed to read input: {}", reason),
+        CustomError::ErrNoTabSize => "zero tab size provided".to_string(),
+        CustomError::ErrInvalidColor => "invalid color mode provided".to_string(),
+        CustomError::ErrUnknownLanguage => "unknown language provided".to_string(),
+        CustomError::ErrInvalidJson(path, line) => format!("{}:{}: parse error", path, line),
+        CustomError::ErrInvalidJsonRecords(count) => {
+            format!("invalid JSON records found: {}", count)
+        }
+        CustomError::ErrWriteFailed => "failed to write output".to_string(),
+    }
+}
+
+fn parse_line_range(range: &str) -> Result<LineRange, CustomError> {
+    let (start, end) = match range.split_once(':') {
+        Some((start, end)) => (parse_line_bound(start)?, parse_line_bound(end)?),
+        None => {
+            let line = parse_line_bound(range)?;
+            if line.is_none() {
+                return Err(CustomError::ErrInvalidLineRange);
+            }
+            (line, line)
+        }
+    };
+
+    // Both Bounds Known Upfront So Check Their Order
+    if let (Some(start), Some(end)) = (start, end) {
+        if start.signum() == end.signum() && start > end {
+            return Err(CustomError::ErrInvalidLineRange);
+        }
+    }
+
+    Ok(LineRange { start, end })
+}
+
+fn parse_line_bound(bound: &str) -> Result<Option<i64>, CustomError> {
+    if bound.is_empty() {
+        return Ok(None);
+    }
+
+    match bound.parse::<i64>() {
+        Ok(0) | Err(_) => Err(CustomError::ErrInvalidLineRange),
+        Ok(line) => Ok(Some(line)),
     }
 }
 
@@ -54,53 +264,147 @@ fn process_input(
     paths: Vec<PathBuf>,
     line_numbers: bool,
     non_blank_line_numbers: bool,
+    line_range: &LineRange,
+    encoding: Encoding,
+    normalization: &Normalization,
+    highlight: &Highlight,
 ) -> Result<String, CustomError> {
-    if paths.len() == 0 {
-        return process_stdin(line_numbers, non_blank_line_numbers);
+    if paths.is_empty() {
+        return process_stdin(
+            line_numbers,
+            non_blank_line_numbers,
+            line_range,
+            encoding,
+            normalization,
+            highlight,
+        );
     }
 
     let mut result: Vec<String> = vec![];
 
-    let content = process_files(paths, line_numbers, non_blank_line_numbers)?;
-    if content != "" {
+    let content = process_files(
+        paths,
+        line_numbers,
+        non_blank_line_numbers,
+        line_range,
+        encoding,
+        normalization,
+        highlight,
+    )?;
+    if !content.is_empty() {
         result.push(content);
     }
 
     Ok(result.join("\n"))
 }
 
-fn process_stdin(line_numbers: bool, non_blank_line_numbers: bool) -> Result<String, CustomError> {
+fn process_stdin(
+    line_numbers: bool,
+    non_blank_line_numbers: bool,
+    line_range: &LineRange,
+    encoding: Encoding,
+    normalization: &Normalization,
+    highlight: &Highlight,
+) -> Result<String, CustomError> {
     if atty::is(atty::Stream::Stdin) {
         return Err(CustomError::ErrNoInput);
     }
 
     let mut readers = vec![];
-    let reader = BufReader::new(io::stdin());
+    let reader = BufReader::new(Decoder::new(io::stdin(), encoding));
     readers.push(reader);
 
-    get_lines::<std::io::Stdin>(readers, line_numbers, non_blank_line_numbers)
+    let languages = [highlight.language];
+
+    get_lines::<Decoder<std::io::Stdin>>(
+        readers,
+        line_numbers,
+        non_blank_line_numbers,
+        line_range,
+        normalization,
+        highlight.is_enabled.then_some(&languages),
+    )
 }
 
 fn process_files(
     paths: Vec<PathBuf>,
     line_numbers: bool,
     non_blank_line_numbers: bool,
+    line_range: &LineRange,
+    encoding: Encoding,
+    normalization: &Normalization,
+    highlight: &Highlight,
 ) -> Result<String, CustomError> {
     let mut readers = vec![];
+    let mut languages = vec![];
 
     for path in paths {
         let file = open_file(&path)?;
-        let reader = BufReader::new(file);
+        let reader = BufReader::new(Decoder::new(file, encoding));
         readers.push(reader);
+        languages.push(highlight.language.or_else(|| highlight::detect_language(&path)));
     }
 
-    get_lines::<std::fs::File>(readers, line_numbers, non_blank_line_numbers)
+    get_lines::<Decoder<std::fs::File>>(
+        readers,
+        line_numbers,
+        non_blank_line_numbers,
+        line_range,
+        normalization,
+        highlight.is_enabled.then_some(&languages),
+    )
+}
+
+/// Broken JSON lines records are reported to `errors` as they are found, and fail the whole
+/// run once every file has been read
+fn process_json<W: Write, E: Write>(
+    paths: Vec<PathBuf>,
+    encoding: Encoding,
+    json_format: &JsonFormat,
+    writer: &mut W,
+    errors: &mut E,
+) -> Result<(), CustomError> {
+    let mut invalid = 0;
+
+    // Stdin Has No Extension, So It Is Always Read As A Stream Of Documents
+    if paths.is_empty() {
+        if atty::is(atty::Stream::Stdin) {
+            return Err(CustomError::ErrNoInput);
+        }
+
+        let reader = BufReader::new(Decoder::new(io::stdin(), encoding));
+        invalid += json::write_documents(reader, Path::new("-"), json_format, writer, errors)?;
+    }
+
+    for path in paths {
+        let file = open_file(&path)?;
+        let reader = BufReader::new(Decoder::new(file, encoding));
+        invalid += json::write_documents(reader, &path, json_format, writer, errors)?;
+    }
+
+    writer.flush().map_err(|_| CustomError::ErrWriteFailed)?;
+    match invalid {
+        0 => Ok(()),
+        _ => Err(CustomError::ErrInvalidJsonRecords(invalid)),
+    }
 }
 
 fn open_file(path: &PathBuf) -> Result<std::fs::File, CustomError> {
     match std::fs::File::open(path) {
-        Err(_) => return Err(CustomError::ErrInvalidPath),
-        Ok(file) => return Ok(file),
+        Err(_) => Err(CustomError::ErrInvalidPath),
+        Ok(file) => Ok(file),
+    }
+}
+
+/// Only bytes the encoding cannot decode are undecodable, other failures such as reading a
+/// directory get a fixed reason for their kind
+fn read_error(error: io::Error) -> CustomError {
+    match error.kind() {
+        io::ErrorKind::InvalidData => CustomError::ErrUndecodableInput,
+        io::ErrorKind::NotFound => CustomError::ErrReadFailed("file not found"),
+        io::ErrorKind::PermissionDenied => CustomError::ErrReadFailed("permission denied"),
+        io::ErrorKind::IsADirectory => CustomError::ErrReadFailed("is a directory"),
+        _ => CustomError::ErrReadFailed("input/output error"),
     }
 }
 
@@ -108,28 +412,132 @@ fn get_lines<T: std::io::Read>(
     readers: Vec<BufReader<T>>,
     line_numbers: bool,
     non_blank_line_numbers: bool,
+    line_range: &LineRange,
+    normalization: &Normalization,
+    languages: Option<&[Option<&'static Language>]>,
 ) -> Result<String, CustomError> {
+    if normalization.expand_tabs == Some(0) {
+        return Err(CustomError::ErrNoTabSize);
+    }
+
     let mut line_index: usize = 1;
-    let mut result = vec![];
-
-    for reader in readers {
-        for line in reader.lines() {
-            let mut line_content = line.unwrap();
-
-						if non_blank_line_numbers && line_content == "" {
-								result.push("".to_string());
-								continue
-						}
-						
-            if line_numbers || non_blank_line_numbers {
-                let prefix = format!("\t{} ", &(line_index).to_string());
+    let mut line_count: i64 = 0;
+    let mut result: VecDeque<(i64, String)> = VecDeque::new();
+
+    'readers: for (reader_index, mut reader) in readers.into_iter().enumerate() {
+        let mut highlighter = languages
+            .and_then(|languages| languages.get(reader_index).copied().flatten())
+            .map(Highlighter::new);
+
+        loop {
+            let mut line = String::new();
+            let count = reader.read_line(&mut line).map_err(read_error)?;
+            if count == 0 {
+                break;
+            }
+            line_count += 1;
+
+            // Stop Reading Once The Range Is Satisfied
+            if let Some(end) = line_range.end {
+                if end > 0 && line_count > end && !line_range.is_from_end() {
+                    break 'readers;
+                }
+            }
+
+            let (line_content, line_ending) = split_line_ending(&line);
+            let mut line_content = normalize_line(line_content, normalization);
+            let line_ending = normalize_line_ending(line_ending, normalization);
+
+            let is_blank_skipped = non_blank_line_numbers && line_content.is_empty();
+
+            if let Some(highlighter) = highlighter.as_mut() {
+                line_content = highlighter.highlight(&line_content);
+            }
+
+            if (line_numbers || non_blank_line_numbers) && !is_blank_skipped {
+                let mut prefix = format!("\t{} ", &(line_index).to_string());
+                if languages.is_some() {
+                    prefix = highlight::paint_gutter(&prefix);
+                }
                 line_content = prefix + &line_content;
                 line_index += 1;
             }
-						
-            result.push(line_content);
+            line_content.push_str(line_ending);
+
+            match line_range.start {
+                Some(start) if start < 0 => {
+                    result.push_back((line_count, line_content));
+                    if result.len() as i64 > -start {
+                        result.pop_front();
+                    }
+                }
+                Some(start) if line_count < start => continue,
+                _ => result.push_back((line_count, line_content)),
+            }
         }
     }
 
-    Ok(result.join("\n"))
+    // Trim Lines Counted From The End Now The Total Is Known
+    if let Some(end) = line_range.end {
+        let last_line = if end < 0 { line_count + end + 1 } else { end };
+        result.retain(|(index, _)| *index <= last_line);
+    }
+
+    Ok(result
+        .into_iter()
+        .map(|(_, line_content)| line_content)
+        .collect::<String>())
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    if let Some(line_content) = line.strip_suffix("\r\n") {
+        return (line_content, "\r\n");
+    }
+
+    if let Some(line_content) = line.strip_suffix('\n') {
+        return (line_content, "\n");
+    }
+
+    // Final Line Without A Newline Is Kept As Is
+    (line, "")
+}
+
+fn normalize_line(line_content: &str, normalization: &Normalization) -> String {
+    let mut line_content = match normalization.expand_tabs {
+        Some(tab_size) => expand_tabs(line_content, tab_size),
+        None => line_content.to_string(),
+    };
+
+    if normalization.strip_trailing_whitespace {
+        line_content.truncate(line_content.trim_end().len());
+    }
+
+    line_content
+}
+
+fn normalize_line_ending<'a>(line_ending: &'a str, normalization: &Normalization) -> &'a str {
+    match line_ending {
+        "" => "",
+        _ if normalization.crlf_to_lf => "\n",
+        _ if normalization.lf_to_crlf => "\r\n",
+        _ => line_ending,
+    }
+}
+
+fn expand_tabs(line_content: &str, tab_size: usize) -> String {
+    let mut result = String::with_capacity(line_content.len());
+    let mut column = 0;
+
+    for character in line_content.chars() {
+        if character == '\t' {
+            let spaces = tab_size - column % tab_size;
+            result.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            result.push(character);
+            column += 1;
+        }
+    }
+
+    result
 }
@@ -1,13 +1,14 @@
 use super::*;
+use highlight::Color;
 use test_case::test_case;
 
-#[test_case(vec!["src/test_data/test.txt".to_string()], false, false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5".to_string(), None; "base case")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], false, false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(), None; "base case")]
 #[test_case(vec!["src/test_data/test_empty.txt".to_string()], false, false, "".to_string(), None; "empty file")]
-#[test_case(vec!["src/test_data/test.txt".to_string()], true, false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "base case + line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], true, false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "base case + line numbers")]
 #[test_case(vec!["src/test_data/test_empty.txt".to_string()], true, false, "".to_string(), None; "empty file + line numbers")]
-#[test_case(vec!["src/test_data/test.txt".to_string()], false, true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "base case + non blank line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], false, true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "base case + non blank line numbers")]
 #[test_case(vec!["src/test_data/test_empty.txt".to_string()], false, true, "".to_string(), None; "empty file + non blank line numbers")]
-#[test_case(vec!["src/test_data/test.txt".to_string()], true, true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "base case + line numbers + non blank line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], true, true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "base case + line numbers + non blank line numbers")]
 #[test_case(vec!["src/test_data/test_empty.txt".to_string()], true, true, "".to_string(), None; "empty file + line numbers + non blank line numbers")]
 fn test_get_lines(
     paths: Vec<String>,
@@ -25,23 +26,23 @@ fn test_get_lines(
     }
 
     match expected_error {
-        None => assert_eq!(expected_result, get_lines(readers, line_numbers, non_blank_line_numbers).unwrap()),
+        None => assert_eq!(expected_result, get_lines(readers, line_numbers, non_blank_line_numbers, &LineRange::default(), &Normalization::default(), None).unwrap()),
         Some(expected_error) => {
-            assert_eq!(Err(expected_error), get_lines(readers, line_numbers, non_blank_line_numbers))
+            assert_eq!(Err(expected_error), get_lines(readers, line_numbers, non_blank_line_numbers, &LineRange::default(), &Normalization::default(), None))
         }
     }
 }
 
 #[test_case(vec![std::path::PathBuf::from("")],  false, false, "".to_string(), Some(CustomError::ErrInvalidPath); "empty path")]
 #[test_case(vec![std::path::PathBuf::from("src/test_data/test_invalid.txt")], false,  false, "".to_string(), Some(CustomError::ErrInvalidPath); "file not present")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], false,  false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\nLine A\nLine B\nLine C\nLine D\nLine E".to_string(), None; "multiple files")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], false,  false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5".to_string(), None; "multiple files with empty file")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")],  false, false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5".to_string(), None; "one file")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], true,  false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n\t6 Line A\n\t7 Line B\n\t8 Line C\n\t9 Line D\n\t10 Line E".to_string(), None; "multiple files + line numbers")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], true,  false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "multiple files with empty file + line numbers")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")],  true, false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "one file + line numbers")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty_content.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], false,  true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n\n\n\t6 Line A\n\t7 Line B\n\t8 Line C\n\t9 Line D\n\t10 Line E".to_string(), None; "multiple files with empty + non blank line numbers")]
-#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], false,  true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5".to_string(), None; "multiple files with empty file + non blank line numbers")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], false,  false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\nLine A\nLine B\nLine C\nLine D\nLine E\n".to_string(), None; "multiple files")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], false,  false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(), None; "multiple files with empty file")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")],  false, false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(), None; "one file")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], true,  false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n\t6 Line A\n\t7 Line B\n\t8 Line C\n\t9 Line D\n\t10 Line E\n".to_string(), None; "multiple files + line numbers")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], true,  false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "multiple files with empty file + line numbers")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")],  true, false, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "one file + line numbers")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty_content.txt"),std::path::PathBuf::from("src/test_data/test_2.txt")], false,  true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n\n\n\t6 Line A\n\t7 Line B\n\t8 Line C\n\t9 Line D\n\t10 Line E\n".to_string(), None; "multiple files with empty + non blank line numbers")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt"),std::path::PathBuf::from("src/test_data/test_empty.txt")], false,  true, "\t1 Line 1\n\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n\t5 Line 5\n".to_string(), None; "multiple files with empty file + non blank line numbers")]
 fn test_process_input(
     paths: Vec<std::path::PathBuf>,
     line_numbers: bool,
@@ -50,7 +51,296 @@ fn test_process_input(
     expected_error: Option<CustomError>,
 ) {
     match expected_error {
-        None => assert_eq!(expected_result, process_input(paths, line_numbers, non_blank_line_numbers).unwrap()),
-        Some(expected_error) => assert_eq!(Err(expected_error), process_input(paths, line_numbers, non_blank_line_numbers)),
+        None => assert_eq!(expected_result, process_input(paths, line_numbers, non_blank_line_numbers, &LineRange::default(), Encoding::Utf8, &Normalization::default(), &Highlight::default()).unwrap()),
+        Some(expected_error) => assert_eq!(Err(expected_error), process_input(paths, line_numbers, non_blank_line_numbers, &LineRange::default(), Encoding::Utf8, &Normalization::default(), &Highlight::default())),
     }
 }
+
+#[test_case("2:4", LineRange { start: Some(2), end: Some(4) }, None; "closed range")]
+#[test_case("3", LineRange { start: Some(3), end: Some(3) }, None; "single line")]
+#[test_case("3:", LineRange { start: Some(3), end: None }, None; "open end")]
+#[test_case(":3", LineRange { start: None, end: Some(3) }, None; "open start")]
+#[test_case("-2:", LineRange { start: Some(-2), end: None }, None; "negative start")]
+#[test_case("2:-2", LineRange { start: Some(2), end: Some(-2) }, None; "negative end")]
+#[test_case(":", LineRange { start: None, end: None }, None; "fully open")]
+#[test_case("", LineRange::default(), Some(CustomError::ErrInvalidLineRange); "empty range")]
+#[test_case("0:3", LineRange::default(), Some(CustomError::ErrInvalidLineRange); "zero start")]
+#[test_case("4:2", LineRange::default(), Some(CustomError::ErrInvalidLineRange); "reversed range")]
+#[test_case("-2:-4", LineRange::default(), Some(CustomError::ErrInvalidLineRange); "reversed negative range")]
+#[test_case("a:b", LineRange::default(), Some(CustomError::ErrInvalidLineRange); "not a number")]
+fn test_parse_line_range(
+    range: &str,
+    expected_result: LineRange,
+    expected_error: Option<CustomError>,
+) {
+    match expected_error {
+        None => assert_eq!(expected_result, parse_line_range(range).unwrap()),
+        Some(expected_error) => assert_eq!(Err(expected_error), parse_line_range(range)),
+    }
+}
+
+#[test_case(vec!["src/test_data/test.txt".to_string()], "2:4", false, false, "Line 2\nLine 3\nLine 4\n".to_string(); "closed range")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "2:4", true, false, "\t2 Line 2\n\t3 Line 3\n\t4 Line 4\n".to_string(); "closed range + line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "4:", true, false, "\t4 Line 4\n\t5 Line 5\n".to_string(); "open end + line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], ":2", false, false, "Line 1\nLine 2\n".to_string(); "open start")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "-2:", true, false, "\t4 Line 4\n\t5 Line 5\n".to_string(); "last lines + line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "2:-2", false, false, "Line 2\nLine 3\nLine 4\n".to_string(); "negative end")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "-4:-3", false, false, "Line 2\nLine 3\n".to_string(); "negative start and end")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "-4:2", false, false, "Line 2\n".to_string(); "negative start + positive end")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "4:200", false, false, "Line 4\nLine 5\n".to_string(); "range past end of file")]
+#[test_case(vec!["src/test_data/test.txt".to_string()], "-200:", false, false, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(); "negative start past start of file")]
+#[test_case(vec!["src/test_data/test.txt".to_string(), "src/test_data/test_2.txt".to_string()], "5:6", true, false, "\t5 Line 5\n\t6 Line A\n".to_string(); "multiple files + line numbers")]
+#[test_case(vec!["src/test_data/test.txt".to_string(), "src/test_data/test_empty_content.txt".to_string(), "src/test_data/test_2.txt".to_string()], "6:8", false, true, "\n\n\t6 Line A\n".to_string(); "multiple files with empty + non blank line numbers")]
+#[test_case(vec!["src/test_data/test_empty.txt".to_string()], "2:4", true, false, "".to_string(); "empty file")]
+fn test_get_lines_range(
+    paths: Vec<String>,
+    range: &str,
+    line_numbers: bool,
+    non_blank_line_numbers: bool,
+    expected_result: String,
+) {
+    let mut readers = vec![];
+
+    for path in paths {
+        let file = std::fs::File::open(path).unwrap();
+        let reader = BufReader::new(file);
+        readers.push(reader)
+    }
+
+    let line_range = parse_line_range(range).unwrap();
+    assert_eq!(
+        expected_result,
+        get_lines(readers, line_numbers, non_blank_line_numbers, &line_range, &Normalization::default(), None).unwrap()
+    );
+}
+
+#[test_case("auto", Encoding::Auto, None; "auto")]
+#[test_case("UTF-8", Encoding::Utf8, None; "utf-8 uppercase")]
+#[test_case("utf-16le", Encoding::Utf16Le, None; "utf-16le")]
+#[test_case("utf_16be", Encoding::Utf16Be, None; "utf-16be underscore")]
+#[test_case("iso-8859-1", Encoding::Latin1, None; "iso-8859-1")]
+#[test_case("latin1", Encoding::Latin1, None; "latin1 alias")]
+#[test_case("ebcdic", Encoding::Auto, Some(CustomError::ErrUnknownEncoding); "unknown encoding")]
+fn test_parse_encoding(
+    name: &str,
+    expected_result: Encoding,
+    expected_error: Option<CustomError>,
+) {
+    match expected_error {
+        None => assert_eq!(expected_result, encoding::parse_encoding(name).unwrap()),
+        Some(expected_error) => assert_eq!(Err(expected_error), encoding::parse_encoding(name)),
+    }
+}
+
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_utf16le_bom.txt")], Encoding::Auto, "Line 1\nLïne 2 — ☃\nLine 3 😀\n".to_string(), None; "auto utf-16le")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_utf16be_bom.txt")], Encoding::Auto, "Line 1\nLïne 2 — ☃\nLine 3 😀\n".to_string(), None; "auto utf-16be")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_utf8_bom.txt")], Encoding::Auto, "Line 1\nLïne 2 — ☃\nLine 3 😀\n".to_string(), None; "auto utf-8 bom")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")], Encoding::Auto, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(), None; "auto without bom")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_utf16le_bom.txt")], Encoding::Utf16Le, "Line 1\nLïne 2 — ☃\nLine 3 😀\n".to_string(), None; "explicit utf-16le")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_latin1.txt")], Encoding::Latin1, "Café\nnaïve\n".to_string(), None; "latin1")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_latin1.txt"), std::path::PathBuf::from("src/test_data/test_2.txt")], Encoding::Latin1, "Café\nnaïve\nLine A\nLine B\nLine C\nLine D\nLine E\n".to_string(), None; "latin1 multiple files")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_latin1.txt")], Encoding::Utf8, "".to_string(), Some(CustomError::ErrUndecodableInput); "latin1 read as utf-8")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_utf16le_bom.txt")], Encoding::Utf8, "".to_string(), Some(CustomError::ErrUndecodableInput); "utf-16le read as utf-8")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_invalid_utf16.txt")], Encoding::Auto, "".to_string(), Some(CustomError::ErrUndecodableInput); "unpaired surrogate")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data")], Encoding::Utf8, "".to_string(), Some(CustomError::ErrReadFailed("is a directory")); "directory")]
+fn test_process_input_encoding(
+    paths: Vec<std::path::PathBuf>,
+    encoding: Encoding,
+    expected_result: String,
+    expected_error: Option<CustomError>,
+) {
+    match expected_error {
+        None => assert_eq!(expected_result, process_input(paths, false, false, &LineRange::default(), encoding, &Normalization::default(), &Highlight::default()).unwrap()),
+        Some(expected_error) => assert_eq!(Err(expected_error), process_input(paths, false, false, &LineRange::default(), encoding, &Normalization::default(), &Highlight::default())),
+    }
+}
+
+#[test_case(Encoding::Utf16Le, 3; "split inside code unit")]
+#[test_case(Encoding::Utf16Le, 4; "split inside surrogate pair")]
+#[test_case(Encoding::Utf16Be, 5; "split after high surrogate byte")]
+fn test_decoder_split_reads(encoding: Encoding, split: usize) {
+    let bytes = match encoding {
+        Encoding::Utf16Be => "a😀b".encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect::<Vec<u8>>(),
+        _ => "a😀b".encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect::<Vec<u8>>(),
+    };
+    let (first, second) = bytes.split_at(split);
+    let mut decoder = Decoder::new(std::io::Read::chain(first, second), encoding);
+
+    let mut result = String::new();
+    std::io::Read::read_to_string(&mut decoder, &mut result).unwrap();
+    assert_eq!("a😀b", result);
+}
+
+#[test_case("src/test_data/test_crlf.txt".to_string(), false, Normalization::default(), "a\tb  \r\nabc\td\r\n\tx \r\nend".to_string(), None; "crlf unchanged")]
+#[test_case("src/test_data/test_crlf.txt".to_string(), false, Normalization { crlf_to_lf: true, ..Default::default() }, "a\tb  \nabc\td\n\tx \nend".to_string(), None; "crlf to lf keeps missing final newline")]
+#[test_case("src/test_data/test_lf.txt".to_string(), false, Normalization { lf_to_crlf: true, ..Default::default() }, "a\tb  \r\nabc\td\r\n\tx \r\nend\r\n".to_string(), None; "lf to crlf keeps final newline")]
+#[test_case("src/test_data/test_crlf.txt".to_string(), false, Normalization { lf_to_crlf: true, ..Default::default() }, "a\tb  \r\nabc\td\r\n\tx \r\nend".to_string(), None; "lf to crlf on crlf input")]
+#[test_case("src/test_data/test_crlf.txt".to_string(), false, Normalization { strip_trailing_whitespace: true, ..Default::default() }, "a\tb\r\nabc\td\r\n\tx\r\nend".to_string(), None; "strip trailing whitespace keeps crlf")]
+#[test_case("src/test_data/test_lf.txt".to_string(), false, Normalization { expand_tabs: Some(8), ..Default::default() }, "a       b  \nabc     d\n        x \nend\n".to_string(), None; "expand tabs default width")]
+#[test_case("src/test_data/test_lf.txt".to_string(), false, Normalization { expand_tabs: Some(4), ..Default::default() }, "a   b  \nabc d\n    x \nend\n".to_string(), None; "expand tabs respects columns")]
+#[test_case("src/test_data/test_lf.txt".to_string(), true, Normalization { expand_tabs: Some(4), strip_trailing_whitespace: true, ..Default::default() }, "\t1 a   b\n\t2 abc d\n\t3     x\n\t4 end\n".to_string(), None; "expand tabs + strip + line numbers")]
+#[test_case("src/test_data/test_lf.txt".to_string(), false, Normalization { expand_tabs: Some(0), ..Default::default() }, "".to_string(), Some(CustomError::ErrNoTabSize); "zero tab size")]
+fn test_get_lines_normalization(
+    path: String,
+    line_numbers: bool,
+    normalization: Normalization,
+    expected_result: String,
+    expected_error: Option<CustomError>,
+) {
+    let file = std::fs::File::open(path).unwrap();
+    let readers = vec![BufReader::new(file)];
+
+    match expected_error {
+        None => assert_eq!(expected_result, get_lines(readers, line_numbers, false, &LineRange::default(), &normalization, None).unwrap()),
+        Some(expected_error) => assert_eq!(Err(expected_error), get_lines(readers, line_numbers, false, &LineRange::default(), &normalization, None)),
+    }
+}
+
+#[test_case(Color::Always, false, None, true; "always without tty")]
+#[test_case(Color::Never, true, None, false; "never with tty")]
+#[test_case(Color::Auto, true, None, true; "auto with tty")]
+#[test_case(Color::Auto, false, None, false; "auto when piped")]
+#[test_case(Color::Auto, true, Some("1"), false; "auto with no color")]
+#[test_case(Color::Auto, true, Some(""), true; "auto with empty no color")]
+#[test_case(Color::Always, true, Some("1"), true; "always overrides no color")]
+fn test_is_color_enabled(color: Color, is_tty: bool, no_color: Option<&str>, expected_result: bool) {
+    assert_eq!(expected_result, highlight::is_color_enabled(color, is_tty, no_color.map(std::ffi::OsString::from)));
+}
+
+#[test_case("src/main.rs", Some("rust"); "rust")]
+#[test_case("script.py", Some("python"); "python")]
+#[test_case("events.jsonl", Some("json"); "json lines")]
+#[test_case("notes.txt", None; "unknown extension")]
+#[test_case("Makefile", None; "no extension")]
+fn test_detect_language(path: &str, expected_result: Option<&str>) {
+    let language = highlight::detect_language(std::path::Path::new(path));
+    assert_eq!(expected_result, language.map(|language| language.name));
+}
+
+#[test_case("rust", "let x = 42; // answer", "\x1b[35mlet\x1b[0m x = \x1b[33m42\x1b[0m; \x1b[90m// answer\x1b[0m"; "rust keywords numbers comments")]
+#[test_case("rust", "println!(\"a \\\" b\")", "println!(\x1b[32m\"a \\\" b\"\x1b[0m)"; "rust escaped string")]
+#[test_case("rust", "fn f<'a>()", "\x1b[35mfn\x1b[0m f<'a>()"; "rust lifetime is not a string")]
+#[test_case("python", "def f(): return 'x' # done", "\x1b[35mdef\x1b[0m f(): \x1b[35mreturn\x1b[0m \x1b[32m'x'\x1b[0m \x1b[90m# done\x1b[0m"; "python")]
+#[test_case("json", "{\"a\": [1, true, null]}", "{\x1b[32m\"a\"\x1b[0m: [\x1b[33m1\x1b[0m, \x1b[35mtrue\x1b[0m, \x1b[35mnull\x1b[0m]}"; "json")]
+#[test_case("rust", "letter", "letter"; "keyword prefix is not a keyword")]
+fn test_highlight(language: &str, line_content: &str, expected_result: &str) {
+    let mut highlighter = Highlighter::new(highlight::parse_language(language).unwrap());
+    assert_eq!(expected_result, highlighter.highlight(line_content));
+}
+
+#[test]
+fn test_highlight_block_comment_across_lines() {
+    let mut highlighter = Highlighter::new(highlight::parse_language("rust").unwrap());
+
+    assert_eq!("x \x1b[90m/* start\x1b[0m", highlighter.highlight("x /* start"));
+    assert_eq!("\x1b[90mmiddle\x1b[0m", highlighter.highlight("middle"));
+    assert_eq!("\x1b[90mend */\x1b[0m \x1b[35mfn\x1b[0m", highlighter.highlight("end */ fn"));
+}
+
+#[test_case("src/test_data/test.rs".to_string(), false, "\x1b[35mfn\x1b[0m main() {\n    \x1b[90m// \"quoted\"\x1b[0m\n}\n".to_string(); "highlighted")]
+#[test_case("src/test_data/test.rs".to_string(), true, "\x1b[2m\t1 \x1b[0m\x1b[35mfn\x1b[0m main() {\n\x1b[2m\t2 \x1b[0m    \x1b[90m// \"quoted\"\x1b[0m\n\x1b[2m\t3 \x1b[0m}\n".to_string(); "highlighted + line numbers")]
+fn test_get_lines_highlight(path: String, line_numbers: bool, expected_result: String) {
+    let file = std::fs::File::open(path).unwrap();
+    let readers = vec![BufReader::new(file)];
+    let languages = [Some(highlight::parse_language("rust").unwrap())];
+
+    assert_eq!(expected_result, get_lines(readers, line_numbers, false, &LineRange::default(), &Normalization::default(), Some(&languages)).unwrap());
+}
+
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.rs")], Highlight { is_enabled: false, language: None }, "fn main() {\n    // \"quoted\"\n}\n".to_string(); "disabled keeps plain output")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.rs")], Highlight { is_enabled: true, language: None }, "\x1b[35mfn\x1b[0m main() {\n    \x1b[90m// \"quoted\"\x1b[0m\n}\n".to_string(); "detected from extension")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")], Highlight { is_enabled: true, language: None }, "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n".to_string(); "unknown extension stays plain")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")], Highlight { is_enabled: true, language: Some(highlight::parse_language("python").unwrap()) }, "Line \x1b[33m1\x1b[0m\nLine \x1b[33m2\x1b[0m\nLine \x1b[33m3\x1b[0m\nLine \x1b[33m4\x1b[0m\nLine \x1b[33m5\x1b[0m\n".to_string(); "language override")]
+fn test_process_input_highlight(paths: Vec<std::path::PathBuf>, highlight: Highlight, expected_result: String) {
+    assert_eq!(expected_result, process_input(paths, false, false, &LineRange::default(), Encoding::Utf8, &Normalization::default(), &highlight).unwrap());
+}
+
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.jsonl")], JsonFormat::default(), "{\n  \"b\": 1,\n  \"a\": {\n    \"d\": [\n      true,\n      null\n    ],\n    \"c\": \"x\"\n  }\n}\n{\n  \"id\": 2,\n  \"name\": \"café\"\n}\n".to_string(), None; "json lines pretty keeps key order")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.jsonl")], JsonFormat { is_sorted: true, ..Default::default() }, "{\n  \"a\": {\n    \"c\": \"x\",\n    \"d\": [\n      true,\n      null\n    ]\n  },\n  \"b\": 1\n}\n{\n  \"id\": 2,\n  \"name\": \"café\"\n}\n".to_string(), None; "json lines sorted keys")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.json")], JsonFormat { is_compact: true, ..Default::default() }, "{\"z\":[1,2],\"y\":\"multi\\nline\"}\n".to_string(), None; "multi line document compact")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.json"), std::path::PathBuf::from("src/test_data/test.jsonl")], JsonFormat { is_compact: true, is_sorted: true }, "{\"y\":\"multi\\nline\",\"z\":[1,2]}\n{\"a\":{\"c\":\"x\",\"d\":[true,null]},\"b\":1}\n{\"id\":2,\"name\":\"café\"}\n".to_string(), None; "multiple files compact sorted")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_invalid.jsonl")], JsonFormat { is_compact: true, ..Default::default() }, "{\"ok\":1}\n{\"ok\":3}\n".to_string(), Some(CustomError::ErrInvalidJsonRecords(1)); "invalid record line")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test.txt")], JsonFormat::default(), "".to_string(), Some(CustomError::ErrInvalidJson("src/test_data/test.txt".to_string(), 1)); "not json")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_empty.txt")], JsonFormat::default(), "".to_string(), None; "empty file")]
+#[test_case(vec![std::path::PathBuf::from("src/test_data/test_invalid.json")], JsonFormat::default(), "".to_string(), Some(CustomError::ErrInvalidPath); "file not present")]
+fn test_process_json(
+    paths: Vec<std::path::PathBuf>,
+    json_format: JsonFormat,
+    expected_result: String,
+    expected_error: Option<CustomError>,
+) {
+    let mut writer = vec![];
+    let result = process_json(
+        paths,
+        Encoding::Utf8,
+        &json_format,
+        &mut writer,
+        &mut vec![],
+    );
+
+    assert_eq!(expected_result, String::from_utf8(writer).unwrap());
+    match expected_error {
+        None => assert_eq!(Ok(()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test]
+fn test_process_json_reports_every_record() {
+    let paths = vec![
+        std::path::PathBuf::from("src/test_data/test_invalid.jsonl"),
+        std::path::PathBuf::from("src/test_data/test_invalid_records.jsonl"),
+    ];
+    let json_format = JsonFormat {
+        is_compact: true,
+        ..Default::default()
+    };
+    let mut writer = vec![];
+    let mut errors = vec![];
+
+    let result = process_json(
+        paths,
+        Encoding::Utf8,
+        &json_format,
+        &mut writer,
+        &mut errors,
+    );
+
+    assert_eq!(Err(CustomError::ErrInvalidJsonRecords(3)), result);
+    assert_eq!(
+        "{\"ok\":1}\n{\"ok\":3}\n[1]\n{\"ok\":4}\n",
+        String::from_utf8(writer).unwrap()
+    );
+    assert_eq!(
+        "Error: src/test_data/test_invalid.jsonl:2: parse error\nError: src/test_data/test_invalid_records.jsonl:2: parse error\nError: src/test_data/test_invalid_records.jsonl:4: parse error\n",
+        String::from_utf8(errors).unwrap()
+    );
+}
+
+#[test_case(&["--lines", "1:2"]; "lines")]
+#[test_case(&["--color", "always"]; "color")]
+#[test_case(&["--language", "rust"]; "language")]
+#[test_case(&["--crlf-to-lf"]; "crlf to lf")]
+#[test_case(&["--lf-to-crlf"]; "lf to crlf")]
+#[test_case(&["--strip-trailing-whitespace"]; "strip trailing whitespace")]
+#[test_case(&["--expand-tabs=4"]; "expand tabs")]
+fn test_json_conflicts(args: &[&str]) {
+    let args = ["rcat", "--json", "src/test_data/test.json"]
+        .iter()
+        .chain(args)
+        .collect::<Vec<&&str>>();
+
+    assert_eq!(
+        clap::ErrorKind::ArgumentConflict,
+        Cli::try_parse_from(args).err().unwrap().kind()
+    );
+}
+
+#[test_case(io::ErrorKind::InvalidData, CustomError::ErrUndecodableInput; "invalid data")]
+#[test_case(io::ErrorKind::NotFound, CustomError::ErrReadFailed("file not found"); "not found")]
+#[test_case(io::ErrorKind::PermissionDenied, CustomError::ErrReadFailed("permission denied"); "permission denied")]
+#[test_case(io::ErrorKind::IsADirectory, CustomError::ErrReadFailed("is a directory"); "is a directory")]
+#[test_case(io::ErrorKind::Other, CustomError::ErrReadFailed("input/output error"); "other")]
+fn test_read_error(kind: io::ErrorKind, expected_error: CustomError) {
+    assert_eq!(expected_error, read_error(io::Error::from(kind)));
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Timelike};
+
+use crate::parse;
+use crate::CustomError;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sign {
+    Plus,
+    Minus,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Year,
+    Month,
+    MonthName,
+    Weekday,
+    WeekdayName,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+/// Applies BSD date(1) `-v` adjustments in order. A sign adds or subtracts, no sign sets the
+/// field. Months clamp to the end of the month, as -v-1m on March 31 means the end of February.
+pub fn adjust_date<T: TimeZone>(
+    date: DateTime<T>,
+    adjustments: &[String],
+) -> Result<DateTime<T>, CustomError> {
+    adjustments.iter().try_fold(date, |date, adjustment| {
+        adjust(&date, adjustment).ok_or_else(|| CustomError::InvalidAdjustment(adjustment.clone()))
+    })
+}
+
+fn adjust<T: TimeZone>(date: &DateTime<T>, adjustment: &str) -> Option<DateTime<T>> {
+    let (sign, field, value) = parse_adjustment(adjustment)?;
+    let signed = match sign {
+        Sign::Minus => -value,
+        _ => value,
+    };
+
+    // Hours, Minutes And Seconds Are Elapsed Time, Everything Else Moves The Calendar
+    match (sign, field) {
+        (Sign::None, _) => (),
+        (_, Field::Hour) => return date.clone().checked_add_signed(Duration::hours(signed)),
+        (_, Field::Minute) => return date.clone().checked_add_signed(Duration::minutes(signed)),
+        (_, Field::Second) => return date.clone().checked_add_signed(Duration::seconds(signed)),
+        _ => (),
+    }
+
+    let local = date.naive_local();
+    let day = local.date();
+    let weekday = day.weekday().num_days_from_sunday() as i64;
+
+    let day = match (sign, field) {
+        (Sign::None, Field::Year) => {
+            parse::add_months(day, (expand_year(value)? - day.year() as i64) * 12)?
+        }
+        (_, Field::Year) => parse::add_months(day, signed * 12)?,
+        (Sign::None, Field::Month | Field::MonthName) if (1..=12).contains(&value) => {
+            parse::clamp_months(day, value - 1 - day.month0() as i64)?
+        }
+        (Sign::None, Field::Month | Field::MonthName) => return None,
+        (_, Field::Month) => parse::clamp_months(day, signed)?,
+        (_, Field::MonthName) => {
+            parse::clamp_months(day, months_to_name(day.month0() as i64, value - 1, sign))?
+        }
+        (Sign::None, Field::Weekday | Field::WeekdayName) if (0..=6).contains(&value) => {
+            day.checked_add_signed(Duration::days(value - weekday))?
+        }
+        (Sign::None, Field::Weekday | Field::WeekdayName) => return None,
+        (_, Field::Weekday) => day.checked_add_signed(Duration::weeks(signed))?,
+        (_, Field::WeekdayName) => {
+            day.checked_add_signed(Duration::days(days_to_name(weekday, value, sign)))?
+        }
+        (Sign::None, Field::Day) => day.with_day(u32::try_from(value).ok()?)?,
+        (_, Field::Day) => day.checked_add_signed(Duration::days(signed))?,
+        (_, Field::Hour | Field::Minute | Field::Second) => day,
+    };
+
+    let mut local = NaiveDateTime::new(day, local.time());
+    let value = u32::try_from(value).ok();
+    local = match field {
+        Field::Hour if sign == Sign::None => local.with_hour(value?)?,
+        Field::Minute if sign == Sign::None => local.with_minute(value?)?,
+        Field::Second if sign == Sign::None => local.with_second(value?)?,
+        _ => local,
+    };
+
+    date.timezone().from_local_datetime(&local).earliest()
+}
+
+/// `[+-]NUMBER[ymwdHMS]`, or `[+-]NAME` for a month or weekday name
+fn parse_adjustment(adjustment: &str) -> Option<(Sign, Field, i64)> {
+    let (sign, rest) = match adjustment.as_bytes().first()? {
+        b'+' => (Sign::Plus, &adjustment[1..]),
+        b'-' => (Sign::Minus, &adjustment[1..]),
+        _ => (Sign::None, adjustment),
+    };
+
+    if rest.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+        return parse_name(&rest.to_lowercase()).map(|(field, value)| (sign, field, value));
+    }
+
+    let (value, unit) = rest.split_at(rest.len().checked_sub(1)?);
+    let field = match unit {
+        "y" => Field::Year,
+        "m" => Field::Month,
+        "w" => Field::Weekday,
+        "d" => Field::Day,
+        "H" => Field::Hour,
+        "M" => Field::Minute,
+        "S" => Field::Second,
+        _ => return None,
+    };
+
+    match value.bytes().all(|byte| byte.is_ascii_digit()) {
+        true => Some((sign, field, value.parse().ok()?)),
+        false => None,
+    }
+}
+
+/// Month names count from 1 and weekday names from 0 for Sunday, as the numbers do
+fn parse_name(name: &str) -> Option<(Field, i64)> {
+    if name.len() < 3 {
+        return None;
+    }
+
+    let abbreviation = &name[..3];
+    if let Some(index) = MONTHS.iter().position(|&month| month == abbreviation) {
+        return Some((Field::MonthName, index as i64 + 1));
+    }
+
+    WEEKDAYS
+        .iter()
+        .position(|&weekday| weekday == abbreviation)
+        .map(|index| (Field::WeekdayName, index as i64))
+}
+
+/// Two digit years below 69 are in the 2000s and the others in the 1900s
+fn expand_year(year: i64) -> Option<i64> {
+    match year {
+        0..=68 => Some(year + 2000),
+        69..=99 => Some(year + 1900),
+        _ => i32::try_from(year).ok().map(i64::from),
+    }
+}
+
+/// +NAME moves to the next such month and -NAME to the previous one, staying in the current
+/// month when it already matches
+fn months_to_name(current: i64, target: i64, sign: Sign) -> i64 {
+    match sign {
+        Sign::Minus => -(current - target).rem_euclid(12),
+        _ => (target - current).rem_euclid(12),
+    }
+}
+
+/// +NAME moves to the next such weekday and -NAME to the previous one, staying on today when
+/// it already matches
+fn days_to_name(current: i64, target: i64, sign: Sign) -> i64 {
+    match sign {
+        Sign::Minus => -(current - target).rem_euclid(7),
+        _ => (target - current).rem_euclid(7),
+    }
+}
@@ -0,0 +1,284 @@
+use chrono::{DateTime, Datelike, Duration, Locale, NaiveDate, Utc};
+
+use crate::format;
+use crate::CustomError;
+
+const MONTH_NAMES: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// 2 September 1752 was followed by 14 September as Britain and its colonies left the Julian
+/// calendar, as cal(1) shows it
+const LAST_JULIAN_DAY: (i32, u32, u32) = (1752, 9, 2);
+const FIRST_GREGORIAN_DAY: (i32, u32, u32) = (1752, 9, 14);
+
+/// The Julian Day Number of 31 December 1 BC in the proleptic Gregorian calendar, day 0 of chrono
+const JULIAN_DAY_OF_CE: i64 = 1_721_425;
+
+const HIGHLIGHT_START: &str = "\x1b[7m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+const GUTTER: &str = "  ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub is_monday_first: bool,
+    pub is_julian: bool,
+    pub is_weeks: bool,
+}
+
+/// Month numbers from 1 to 12, or English names such as mar or September
+pub fn parse_month(month: &str) -> Result<u32, CustomError> {
+    let invalid = || CustomError::InvalidMonth(month.to_string());
+
+    if let Ok(number) = month.parse::<u32>() {
+        return match (1..=12).contains(&number) {
+            true => Ok(number),
+            false => Err(invalid()),
+        };
+    }
+
+    let name = month.to_lowercase();
+    match name.len() >= 3 {
+        true => MONTH_NAMES
+            .iter()
+            .position(|full| full.starts_with(&name))
+            .map(|index| index as u32 + 1)
+            .ok_or_else(invalid),
+        false => Err(invalid()),
+    }
+}
+
+pub fn parse_year(year: &str) -> Result<i32, CustomError> {
+    match year.parse::<i32>() {
+        Ok(year) if (1..=9999).contains(&year) => Ok(year),
+        _ => Err(CustomError::InvalidYear(year.to_string())),
+    }
+}
+
+/// Julian years are leap every four years, up to and including 1752
+pub fn is_leap_year(year: i32) -> bool {
+    match year <= LAST_JULIAN_DAY.0 {
+        true => year % 4 == 0,
+        false => year % 4 == 0 && (year % 100 != 0 || year % 400 == 0),
+    }
+}
+
+/// The length of the month as named, so September 1752 still has 30 days
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days before the reform count as if the skipped days had happened, as cal -j shows them
+pub fn day_of_year(year: i32, month: u32, day: u32) -> u32 {
+    (1..month)
+        .map(|month| days_in_month(year, month))
+        .sum::<u32>()
+        + day
+}
+
+/// The day `year-month-day` names, as a proleptic Gregorian date for chrono. Dates up to the
+/// reform are Julian, and the days it skipped are `None`.
+pub fn civil_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let date = (year, month, day);
+    match (date <= LAST_JULIAN_DAY, date < FIRST_GREGORIAN_DAY) {
+        (true, _) => julian_date(year, month, day),
+        (false, true) => None,
+        (false, false) => NaiveDate::from_ymd_opt(year, month, day),
+    }
+}
+
+fn julian_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    // March Starts The Computational Year, So The Leap Day Falls At Its End
+    let before_march = (14 - month as i64) / 12;
+    let year = year as i64 + 4800 - before_march;
+    let month = month as i64 + 12 * before_march - 3;
+    let julian_day = day as i64 + (153 * month + 2) / 5 + 365 * year + year / 4 - 32083;
+
+    NaiveDate::from_num_days_from_ce_opt((julian_day - JULIAN_DAY_OF_CE).try_into().ok()?)
+}
+
+/// One month as lines of the same width: the title, the weekday names and six weeks, with
+/// `today` highlighted
+pub fn month_lines(
+    year: i32,
+    month: u32,
+    is_titled_with_year: bool,
+    layout: Layout,
+    locale: Locale,
+    today: Option<NaiveDate>,
+) -> Vec<String> {
+    let cell_width = match layout.is_julian {
+        true => 3,
+        false => 2,
+    };
+    let week_width = match layout.is_weeks {
+        true => 3,
+        false => 0,
+    };
+    let width = week_width + cell_width * 7 + 6;
+
+    let days = (1..=days_in_month(year, month))
+        .filter_map(|day| civil_date(year, month, day).map(|date| (day, date)))
+        .collect::<Vec<(u32, NaiveDate)>>();
+    let first = days.first().map_or(NaiveDate::MIN, |&(_, date)| date);
+    let start = first - Duration::days(column(first, layout) as i64);
+
+    // The Julian First Of The Month May Fall In Another Gregorian Month, So Name A Modern One
+    let named = NaiveDate::from_ymd(2000, month, 1);
+    let name = format::format_name(&at_midnight(named), 'B', locale);
+    let title = match is_titled_with_year {
+        true => format!("{} {}", name, year),
+        false => name,
+    };
+
+    let weekdays = (0..7)
+        .map(|column| {
+            let name =
+                format::format_name(&at_midnight(start + Duration::days(column)), 'a', locale);
+            let name = name.chars().take(2).collect::<String>();
+            format!("{:>1$}", name, cell_width)
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let mut weeks = vec![vec![" ".repeat(cell_width); 7]; 6];
+    for (day, date) in days {
+        let index = (date - start).num_days() as usize;
+        let number = match layout.is_julian {
+            true => day_of_year(year, month, day),
+            false => day,
+        };
+        weeks[index / 7][index % 7] = cell(number, cell_width, Some(date) == today);
+    }
+
+    let mut lines = vec![
+        pad(&center(&title, width - week_width), width, week_width),
+        pad(&weekdays, width, week_width),
+    ];
+    lines.extend(weeks.iter().enumerate().map(|(row, cells)| {
+        let is_empty = cells.iter().all(|cell| cell.trim().is_empty());
+        let week = match (layout.is_weeks, is_empty) {
+            (true, false) => {
+                let monday = start + Duration::days(row as i64 * 7 + column_of_monday(layout));
+                format!("{:>2} ", monday.iso_week().week())
+            }
+            _ => " ".repeat(week_width),
+        };
+        format!("{}{}", week, cells.join(" "))
+    }));
+
+    lines
+}
+
+/// Months side by side, each `lines` block the same height
+pub fn join_months(months: &[Vec<String>]) -> Vec<String> {
+    let height = months.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..height)
+        .map(|index| {
+            months
+                .iter()
+                .map(|lines| lines.get(index).map_or("", String::as_str))
+                .collect::<Vec<&str>>()
+                .join(GUTTER)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// The whole year, three months to a row or two with day of year numbers
+pub fn year_lines(
+    year: i32,
+    layout: Layout,
+    locale: Locale,
+    today: Option<NaiveDate>,
+) -> Vec<String> {
+    let per_row = match layout.is_julian {
+        true => 2,
+        false => 3,
+    };
+
+    let months = (1..=12)
+        .map(|month| month_lines(year, month, false, layout, locale, today))
+        .collect::<Vec<Vec<String>>>();
+    let width = months[0][0].chars().count() * per_row + GUTTER.len() * (per_row - 1);
+
+    let mut lines = vec![center(&year.to_string(), width).trim_end().to_string()];
+    for row in months.chunks(per_row) {
+        let mut row = join_months(row);
+        while row.last().is_some_and(String::is_empty) {
+            row.pop();
+        }
+
+        lines.push(String::new());
+        lines.extend(row);
+    }
+
+    lines
+}
+
+/// The columns run from Sunday, or Monday with -m
+fn column(date: NaiveDate, layout: Layout) -> u32 {
+    match layout.is_monday_first {
+        true => date.weekday().num_days_from_monday(),
+        false => date.weekday().num_days_from_sunday(),
+    }
+}
+
+/// ISO weeks start on Monday, which is the second column when weeks start on Sunday
+fn column_of_monday(layout: Layout) -> i64 {
+    match layout.is_monday_first {
+        true => 0,
+        false => 1,
+    }
+}
+
+fn cell(number: u32, width: usize, is_today: bool) -> String {
+    let number = number.to_string();
+    let padding = " ".repeat(width.saturating_sub(number.len()));
+
+    match is_today {
+        true => format!("{}{}{}{}", padding, HIGHLIGHT_START, number, HIGHLIGHT_END),
+        false => format!("{}{}", padding, number),
+    }
+}
+
+fn center(text: &str, width: usize) -> String {
+    let left = width.saturating_sub(text.chars().count()) / 2;
+
+    format!("{}{}", " ".repeat(left), text)
+}
+
+/// Indents `line` past the week numbers and fills it out to `width`
+fn pad(line: &str, width: usize, indent: usize) -> String {
+    let line = format!("{}{}", " ".repeat(indent), line);
+    let fill = width.saturating_sub(line.chars().count());
+
+    format!("{}{}", line, " ".repeat(fill))
+}
+
+fn at_midnight(date: NaiveDate) -> DateTime<Utc> {
+    DateTime::from_utc(date.and_hms(0, 0, 0), Utc)
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+
+/// Where the current time comes from. A fixed clock never moves, so output that depends on it
+/// is the same on every run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    System,
+    Fixed(DateTime<Utc>),
+}
+
+impl Clock {
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Fixed(now) => *now,
+        }
+    }
+}
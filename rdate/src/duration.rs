@@ -0,0 +1,409 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+
+use crate::parse;
+use crate::CustomError;
+
+const NANOSECONDS_PER_SECOND: i128 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Years,
+    Months,
+    Weeks,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+const UNITS: [(&[&str], Unit); 7] = [
+    (&["y", "yr", "yrs", "year", "years"], Unit::Years),
+    (&["mo", "mon", "month", "months"], Unit::Months),
+    (&["w", "wk", "wks", "week", "weeks"], Unit::Weeks),
+    (&["d", "day", "days"], Unit::Days),
+    (&["h", "hr", "hrs", "hour", "hours"], Unit::Hours),
+    (&["m", "min", "mins", "minute", "minutes"], Unit::Minutes),
+    (&["s", "sec", "secs", "second", "seconds"], Unit::Seconds),
+];
+
+const DATE_DESIGNATORS: [(char, Unit); 4] = [
+    ('y', Unit::Years),
+    ('m', Unit::Months),
+    ('w', Unit::Weeks),
+    ('d', Unit::Days),
+];
+
+const TIME_DESIGNATORS: [(char, Unit); 3] = [
+    ('h', Unit::Hours),
+    ('m', Unit::Minutes),
+    ('s', Unit::Seconds),
+];
+
+/// A calendar aware duration: months and days move the wall clock, the rest is elapsed time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub months: i64,
+    pub days: i64,
+    pub elapsed: Duration,
+}
+
+impl Span {
+    fn zero() -> Span {
+        Span {
+            months: 0,
+            days: 0,
+            elapsed: Duration::zero(),
+        }
+    }
+
+    fn negate(self) -> Span {
+        Span {
+            months: -self.months,
+            days: -self.days,
+            elapsed: -self.elapsed,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.months < 0 || self.days < 0 || self.elapsed < Duration::zero()
+    }
+
+    /// Adds `number` of `unit`, where only hours, minutes and seconds may have a fraction
+    fn add(&mut self, unit: Unit, number: &str) -> Option<()> {
+        let (whole, fraction) = number.split_once(['.', ',']).unwrap_or((number, ""));
+        let is_digits = |value: &str| value.bytes().all(|byte| byte.is_ascii_digit());
+        if whole.is_empty() && fraction.is_empty()
+            || !is_digits(whole)
+            || !is_digits(fraction)
+            || fraction.len() > 9
+        {
+            return None;
+        }
+        let whole: i64 = match whole {
+            "" => 0,
+            _ => whole.parse().ok()?,
+        };
+
+        let seconds: i128 = match unit {
+            Unit::Hours => 3600,
+            Unit::Minutes => 60,
+            Unit::Seconds => 1,
+            _ if !fraction.is_empty() => return None,
+            _ => 0,
+        };
+        match unit {
+            Unit::Years => self.months = self.months.checked_add(whole.checked_mul(12)?)?,
+            Unit::Months => self.months = self.months.checked_add(whole)?,
+            Unit::Weeks => self.days = self.days.checked_add(whole.checked_mul(7)?)?,
+            Unit::Days => self.days = self.days.checked_add(whole)?,
+            _ => {
+                let fraction: i128 = format!("{:0<9}", fraction).parse().ok()?;
+                let nanoseconds = (whole as i128 * NANOSECONDS_PER_SECOND + fraction) * seconds;
+                let nanoseconds = Duration::nanoseconds(i64::try_from(nanoseconds).ok()?);
+                self.elapsed = self.elapsed.checked_add(&nanoseconds)?;
+            }
+        }
+
+        Some(())
+    }
+}
+
+/// Parses a duration such as "90 days", "1h30m", "2 months 3 hours" or an ISO 8601 duration
+/// like P1M2DT3H. A leading minus negates all of it, and a bare number is seconds.
+pub fn parse_duration(value: &str) -> Result<Span, CustomError> {
+    let trimmed = value.trim();
+    let (is_negative, rest) = match trimmed.as_bytes().first() {
+        Some(b'-') => (true, &trimmed[1..]),
+        Some(b'+') => (false, &trimmed[1..]),
+        _ => (false, trimmed),
+    };
+
+    let span = match rest.strip_prefix(['P', 'p']) {
+        Some(iso) => parse_iso_8601(iso),
+        None => parse_words(rest),
+    };
+
+    match span {
+        Some(span) if is_negative => Ok(span.negate()),
+        Some(span) => Ok(span),
+        None => Err(CustomError::InvalidDuration(value.to_string())),
+    }
+}
+
+/// Numbers each followed by a unit, with or without spaces between them
+fn parse_words(value: &str) -> Option<Span> {
+    let value = value.to_lowercase();
+    let mut span = Span::zero();
+    let mut rest = value.trim_start_matches([' ', ',']);
+
+    let (number, after) = split_number(rest);
+    if !number.is_empty() && after.is_empty() {
+        span.add(Unit::Seconds, number)?;
+        return Some(span);
+    }
+
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest);
+        let after = after.trim_start();
+        let length = after
+            .find(|character: char| !character.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let name = &after[..length];
+
+        let &(_, unit) = UNITS.iter().find(|(names, _)| names.contains(&name))?;
+        span.add(unit, number)?;
+        rest = after[length..].trim_start_matches([' ', ',']);
+    }
+
+    match value.trim().is_empty() {
+        true => None,
+        false => Some(span),
+    }
+}
+
+/// `PnYnMnWnDTnHnMnS`, where any part may be left out but not all of them
+fn parse_iso_8601(value: &str) -> Option<Span> {
+    let (date, time) = match value.split_once(['T', 't']) {
+        Some((_, "")) => return None,
+        Some((date, time)) => (date, time),
+        None => (value, ""),
+    };
+    if date.is_empty() && time.is_empty() {
+        return None;
+    }
+
+    let mut span = Span::zero();
+    for (part, units) in [
+        (date, DATE_DESIGNATORS.as_slice()),
+        (time, TIME_DESIGNATORS.as_slice()),
+    ] {
+        let mut rest = part;
+        let mut units = units.iter();
+
+        while !rest.is_empty() {
+            let (number, after) = split_number(rest);
+            let designator = after.chars().next()?.to_ascii_lowercase();
+
+            // Designators Must Come In Order, Each At Most Once
+            let &(_, unit) = units.find(|(name, _)| *name == designator)?;
+            span.add(unit, number)?;
+            rest = &after[1..];
+        }
+    }
+
+    Some(span)
+}
+
+/// Splits a leading number, with an optional fraction, from the rest of `value`
+fn split_number(value: &str) -> (&str, &str) {
+    let length = value
+        .find(|character: char| !character.is_ascii_digit() && !matches!(character, '.' | ','))
+        .unwrap_or(value.len());
+
+    value.split_at(length)
+}
+
+/// Adds `span` to `date`, moving the calendar in local time first, as "2 days" keeps the time
+/// of day across a DST change, and then adding the elapsed time
+pub fn add_span<T: TimeZone>(date: &DateTime<T>, span: &Span) -> Option<DateTime<T>> {
+    let date = match (span.months, span.days) {
+        (0, 0) => date.clone(),
+        _ => {
+            let local = date.naive_local();
+            let day = parse::clamp_months(local.date(), span.months)?
+                .checked_add_signed(Duration::days(span.days))?;
+            resolve_local(&date.timezone(), &day.and_time(local.time()))?
+        }
+    };
+
+    date.checked_add_signed(span.elapsed)
+}
+
+/// Repeated local times resolve to the earlier one, and times skipped by a DST change move
+/// forward by the length of the gap, so 02:30 on the day clocks go from 02:00 to 03:00 is 03:30
+fn resolve_local<T: TimeZone>(timezone: &T, local: &NaiveDateTime) -> Option<DateTime<T>> {
+    match timezone.from_local_datetime(local) {
+        LocalResult::Single(date) | LocalResult::Ambiguous(date, _) => Some(date),
+        LocalResult::None => {
+            let before = timezone
+                .from_local_datetime(&local.checked_sub_signed(Duration::days(1))?)
+                .earliest()?;
+            let offset = before.offset().fix().local_minus_utc();
+            let utc = local.checked_sub_signed(Duration::seconds(offset as i64))?;
+            Some(timezone.from_utc_datetime(&utc))
+        }
+    }
+}
+
+/// Splits the time from `start` to `end` into whole months, then whole days, then elapsed time,
+/// so that adding the result to `start` gives `end`
+pub fn between<T: TimeZone>(start: &DateTime<T>, end: &DateTime<T>) -> Option<Span> {
+    if end < start {
+        return between(end, start).map(Span::negate);
+    }
+
+    let calendar = |months, days| {
+        add_span(
+            start,
+            &Span {
+                months,
+                days,
+                elapsed: Duration::zero(),
+            },
+        )
+    };
+    let (start_local, end_local) = (start.naive_local(), end.naive_local());
+
+    let mut months = (end_local.year() as i64 - start_local.year() as i64) * 12
+        + end_local.month0() as i64
+        - start_local.month0() as i64;
+    while months > 0 && calendar(months, 0)? > *end {
+        months -= 1;
+    }
+
+    let moved = parse::clamp_months(start_local.date(), months)?;
+    let mut days = (end_local.date() - moved).num_days().max(0);
+    while days > 0 && calendar(months, days)? > *end {
+        days -= 1;
+    }
+
+    Some(Span {
+        months,
+        days,
+        elapsed: end.clone().signed_duration_since(calendar(months, days)?),
+    })
+}
+
+/// Whole seconds, with as much of a fraction as is needed
+pub fn format_seconds(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let nanoseconds = duration.num_nanoseconds().map_or_else(
+        || duration.num_seconds().unsigned_abs() as u128 * NANOSECONDS_PER_SECOND as u128,
+        |nanoseconds| nanoseconds.unsigned_abs() as u128,
+    );
+    let (seconds, fraction) = (
+        nanoseconds / NANOSECONDS_PER_SECOND as u128,
+        nanoseconds % NANOSECONDS_PER_SECOND as u128,
+    );
+
+    match fraction {
+        0 => format!("{}{}", sign, seconds),
+        _ => format!(
+            "{}{}.{}",
+            sign,
+            seconds,
+            format!("{:09}", fraction).trim_end_matches('0')
+        ),
+    }
+}
+
+/// Like `format_seconds`, with a plus on positive values and zero
+pub fn format_signed_seconds(duration: Duration) -> String {
+    match duration < Duration::zero() {
+        true => format_seconds(duration),
+        false => format!("+{}", format_seconds(duration)),
+    }
+}
+
+/// `PnYnMnDTnHnMnS` leaving out the parts that are zero, with a minus for negative spans
+pub fn format_iso_8601(span: &Span) -> String {
+    let (sign, span) = match span.is_negative() {
+        true => ("-", span.negate()),
+        false => ("", *span),
+    };
+    let (years, months, days, hours, minutes, seconds) = split(&span);
+
+    let mut result = format!("{}P", sign);
+    for (value, designator) in [(years, 'Y'), (months, 'M'), (days, 'D')] {
+        if value > 0 {
+            result.push_str(&format!("{}{}", value, designator));
+        }
+    }
+
+    let seconds = format_seconds(seconds);
+    if hours > 0 || minutes > 0 || seconds != "0" {
+        result.push('T');
+        for (value, designator) in [(hours, 'H'), (minutes, 'M')] {
+            if value > 0 {
+                result.push_str(&format!("{}{}", value, designator));
+            }
+        }
+        if seconds != "0" {
+            result.push_str(&format!("{}S", seconds));
+        }
+    }
+
+    match result.ends_with('P') {
+        true => "PT0S".to_string(),
+        false => result,
+    }
+}
+
+/// "1 year 2 months 3 hours", in whole seconds, ending in "ago" for negative spans
+pub fn format_human(span: &Span) -> String {
+    let (suffix, span) = match span.is_negative() {
+        true => (" ago", span.negate()),
+        false => ("", *span),
+    };
+    let (years, months, days, hours, minutes, seconds) = split(&span);
+
+    let words: Vec<String> = [
+        (years, "year"),
+        (months, "month"),
+        (days, "day"),
+        (hours, "hour"),
+        (minutes, "minute"),
+        (seconds.num_seconds(), "second"),
+    ]
+    .iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, name)| match value {
+        1 => format!("1 {}", name),
+        _ => format!("{} {}s", value, name),
+    })
+    .collect();
+
+    match words.is_empty() {
+        true => "0 seconds".to_string(),
+        false => format!("{}{}", words.join(" "), suffix),
+    }
+}
+
+/// "H:MM:SS" as a stopwatch shows it, with tenths of a second when `is_tenths`. Hours keep
+/// counting past a day.
+pub fn format_clock(duration: Duration, is_tenths: bool) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let duration = match duration < Duration::zero() {
+        true => -duration,
+        false => duration,
+    };
+    let (hours, minutes, seconds) = (
+        duration.num_hours(),
+        duration.num_minutes() % 60,
+        duration.num_seconds() % 60,
+    );
+
+    match is_tenths {
+        true => {
+            let tenths = duration.num_milliseconds() % 1000 / 100;
+            format!("{}{}:{:02}:{:02}.{}", sign, hours, minutes, seconds, tenths)
+        }
+        false => format!("{}{}:{:02}:{:02}", sign, hours, minutes, seconds),
+    }
+}
+
+/// Years, months, days, hours and minutes of a positive span, and the seconds left over
+fn split(span: &Span) -> (i64, i64, i64, i64, i64, Duration) {
+    let hours = span.elapsed.num_hours();
+    let minutes = (span.elapsed - Duration::hours(hours)).num_minutes();
+    let seconds = span.elapsed - Duration::hours(hours) - Duration::minutes(minutes);
+
+    (
+        span.months / 12,
+        span.months % 12,
+        span.days,
+        hours,
+        minutes,
+        seconds,
+    )
+}
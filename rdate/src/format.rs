@@ -0,0 +1,291 @@
+use chrono::{DateTime, Datelike, Locale, Offset, TimeZone, Timelike};
+use std::fmt::Display;
+
+use crate::CustomError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Padding {
+    Default,
+    None,
+    Space,
+    Zero,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Default,
+    Upper,
+    Swap,
+}
+
+#[derive(Debug)]
+struct Spec {
+    padding: Padding,
+    case: Case,
+    width: Option<usize>,
+    colons: usize,
+    conversion: char,
+}
+
+enum Field {
+    /// A value with its default width and padding character
+    Number(i64, usize, char),
+    Text(String),
+}
+
+/// Formats `date` like GNU date(1), including the `-_0^#` flags, field widths, `%N` and `%:z`,
+/// with names and the `%c`, `%x` and `%X` layouts from `locale`
+pub fn format_date<T: TimeZone>(
+    date: &DateTime<T>,
+    format: &str,
+    locale: Locale,
+) -> Result<String, CustomError>
+where
+    T::Offset: Display,
+{
+    let mut result = String::with_capacity(format.len() * 2);
+    let mut rest = format;
+
+    while let Some(index) = rest.find('%') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let (spec, length) = parse_spec(rest)?;
+        let invalid = || CustomError::InvalidFormat(format!("%{}", &rest[..length]));
+
+        result.push_str(&format_spec(date, &spec, locale).ok_or_else(invalid)?);
+        rest = &rest[length..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Parses the flags, width, colons and conversion after a `%`, returning the spec and its length
+fn parse_spec(value: &str) -> Result<(Spec, usize), CustomError> {
+    let mut spec = Spec {
+        padding: Padding::Default,
+        case: Case::Default,
+        width: None,
+        colons: 0,
+        conversion: '%',
+    };
+    let bytes = value.as_bytes();
+    let mut index = 0;
+
+    while let Some(&flag) = bytes.get(index) {
+        match flag {
+            b'-' => spec.padding = Padding::None,
+            b'_' => spec.padding = Padding::Space,
+            b'0' => spec.padding = Padding::Zero,
+            b'^' => spec.case = Case::Upper,
+            b'#' => spec.case = Case::Swap,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    let digits = bytes[index..]
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    if digits > 0 {
+        spec.width = value[index..index + digits].parse().ok();
+        index += digits;
+    }
+
+    spec.colons = bytes[index..]
+        .iter()
+        .take_while(|&&byte| byte == b':')
+        .count();
+    index += spec.colons;
+
+    // The E And O Modifiers Only Select Alternative Numerals Outside The C Locale
+    if matches!(bytes.get(index), Some(b'E' | b'O')) {
+        index += 1;
+    }
+
+    match value[index..].chars().next() {
+        Some(conversion) => {
+            spec.conversion = conversion;
+            Ok((spec, index + conversion.len_utf8()))
+        }
+        None => Err(CustomError::InvalidFormat(format!("%{}", value))),
+    }
+}
+
+/// Formats a single conversion, or `None` when it is not a known specifier
+fn format_spec<T: TimeZone>(date: &DateTime<T>, spec: &Spec, locale: Locale) -> Option<String>
+where
+    T::Offset: Display,
+{
+    if spec.colons > 0 && (spec.conversion != 'z' || spec.colons > 3) {
+        return None;
+    }
+
+    let field = match spec.conversion {
+        '%' => Field::Text("%".to_string()),
+        'n' => Field::Text("\n".to_string()),
+        't' => Field::Text("\t".to_string()),
+        'a' | 'A' | 'b' | 'B' | 'h' | 'c' | 'p' | 'P' | 'r' | 'x' | 'X' => {
+            Field::Text(format_name(date, spec.conversion, locale))
+        }
+        'C' => Field::Number(date.year().div_euclid(100) as i64, 2, '0'),
+        'd' => Field::Number(date.day() as i64, 2, '0'),
+        'D' => Field::Text(format_date(date, "%m/%d/%y", locale).ok()?),
+        'e' => Field::Number(date.day() as i64, 2, ' '),
+        'F' => return Some(format_iso_date(date, spec)),
+        'g' => Field::Number(date.iso_week().year().rem_euclid(100) as i64, 2, '0'),
+        'G' => Field::Number(date.iso_week().year() as i64, 4, '0'),
+        'H' => Field::Number(date.hour() as i64, 2, '0'),
+        'I' => Field::Number(date.hour12().1 as i64, 2, '0'),
+        'j' => Field::Number(date.ordinal() as i64, 3, '0'),
+        'k' => Field::Number(date.hour() as i64, 2, ' '),
+        'l' => Field::Number(date.hour12().1 as i64, 2, ' '),
+        'm' => Field::Number(date.month() as i64, 2, '0'),
+        'M' => Field::Number(date.minute() as i64, 2, '0'),
+        'N' => return Some(format_nanoseconds(date, spec)),
+        'q' => Field::Number(date.month0() as i64 / 3 + 1, 1, '0'),
+        'R' => Field::Text(format_date(date, "%H:%M", locale).ok()?),
+        's' => Field::Number(date.timestamp(), 1, '0'),
+        'S' => Field::Number(date.second() as i64, 2, '0'),
+        'T' => Field::Text(format_date(date, "%H:%M:%S", locale).ok()?),
+        'u' => Field::Number(date.weekday().number_from_monday() as i64, 1, '0'),
+        'U' => Field::Number(
+            week_of_year(date.ordinal0(), date.weekday().num_days_from_sunday()),
+            2,
+            '0',
+        ),
+        'V' => Field::Number(date.iso_week().week() as i64, 2, '0'),
+        'w' => Field::Number(date.weekday().num_days_from_sunday() as i64, 1, '0'),
+        'W' => Field::Number(
+            week_of_year(date.ordinal0(), date.weekday().num_days_from_monday()),
+            2,
+            '0',
+        ),
+        'y' => Field::Number(date.year().rem_euclid(100) as i64, 2, '0'),
+        'Y' => Field::Number(date.year() as i64, 4, '0'),
+        'z' => return Some(format_offset(date.offset().fix().local_minus_utc(), spec)),
+        'Z' => Field::Text(date.offset().to_string()),
+        _ => return None,
+    };
+
+    let result = match field {
+        Field::Number(value, width, fill) => {
+            let sign = if value < 0 { "-" } else { "" };
+            let digits = value.unsigned_abs().to_string();
+            pad(
+                sign,
+                &digits,
+                spec.width.unwrap_or(width),
+                fill_for(spec.padding, fill),
+            )
+        }
+        Field::Text(text) => {
+            let text = match (spec.case, spec.conversion) {
+                (Case::Default, _) => text,
+                (Case::Swap, 'p' | 'Z') => text.to_lowercase(),
+                _ => text.to_uppercase(),
+            };
+            pad(
+                "",
+                &text,
+                spec.width.unwrap_or(0),
+                fill_for(spec.padding, ' '),
+            )
+        }
+    };
+
+    Some(result)
+}
+
+/// Names and layouts from the locale's LC_TIME. The POSIX tables put stray newlines in `%c`, so
+/// the C locale keeps chrono's own English ones.
+pub fn format_name<T: TimeZone>(date: &DateTime<T>, conversion: char, locale: Locale) -> String
+where
+    T::Offset: Display,
+{
+    let pattern = format!("%{}", conversion);
+
+    match locale {
+        Locale::POSIX => date.format(&pattern).to_string(),
+        _ => date.format_localized(&pattern, locale).to_string(),
+    }
+}
+
+/// Weeks starting on the first Sunday (`%U`) or Monday (`%W`), with the days before it in week 0
+fn week_of_year(ordinal0: u32, days_from_week_start: u32) -> i64 {
+    ((ordinal0 + 7 - days_from_week_start) / 7) as i64
+}
+
+/// `%F` is `%+4Y-%m-%d`, so a width widens the year rather than the whole date
+fn format_iso_date<T: TimeZone>(date: &DateTime<T>, spec: &Spec) -> String {
+    let year = date.year() as i64;
+    let sign = if year < 0 { "-" } else { "" };
+    let width = spec.width.map_or(4, |width| width.saturating_sub(6));
+    let year = pad(
+        sign,
+        &year.unsigned_abs().to_string(),
+        width,
+        fill_for(spec.padding, '0'),
+    );
+
+    format!("{}-{:02}-{:02}", year, date.month(), date.day())
+}
+
+/// `%N` is always zero padded on the right, and a width selects how many digits to keep
+fn format_nanoseconds<T: TimeZone>(date: &DateTime<T>, spec: &Spec) -> String {
+    let digits = format!("{:09}", date.nanosecond() % 1_000_000_000);
+
+    match spec.width.unwrap_or(9) {
+        width if width <= 9 => digits[..width].to_string(),
+        width => format!("{}{}", digits, "0".repeat(width - 9)),
+    }
+}
+
+/// `%z` is +hhmm, `%:z` +hh:mm, `%::z` +hh:mm:ss and `%:::z` only as precise as needed
+fn format_offset(offset: i32, spec: &Spec) -> String {
+    let sign = if offset < 0 { "-" } else { "+" };
+    let offset = offset.unsigned_abs();
+    let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+
+    let body = match spec.colons {
+        0 => format!("{:02}{:02}", hours, minutes),
+        1 => format!("{:02}:{:02}", hours, minutes),
+        3 if seconds == 0 && minutes == 0 => format!("{:02}", hours),
+        3 if seconds == 0 => format!("{:02}:{:02}", hours, minutes),
+        _ => format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+    };
+
+    pad(
+        sign,
+        &body,
+        spec.width.unwrap_or(0),
+        fill_for(spec.padding, '0'),
+    )
+}
+
+fn fill_for(padding: Padding, default: char) -> Option<char> {
+    match padding {
+        Padding::Default => Some(default),
+        Padding::None => None,
+        Padding::Space => Some(' '),
+        Padding::Zero => Some('0'),
+    }
+}
+
+/// Pads to `width`, putting zeros between the sign and the value and spaces before both
+fn pad(sign: &str, value: &str, width: usize, fill: Option<char>) -> String {
+    let length = sign.len() + value.chars().count();
+
+    match fill {
+        Some(fill) if width > length => {
+            let padding = fill.to_string().repeat(width - length);
+            match fill {
+                '0' => format!("{}{}{}", sign, padding, value),
+                _ => format!("{}{}{}", padding, sign, value),
+            }
+        }
+        _ => format!("{}{}", sign, value),
+    }
+}
@@ -1,61 +1,1178 @@
 use clap::Parser;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 
-// TODO
-// -v
+mod adjust;
+mod calendar;
+mod clock;
+mod duration;
+mod format;
+mod ntp;
+mod parse;
+mod time_protocol;
+mod timer;
+mod zone;
 
 #[cfg(test)]
 mod tests;
 
+const DEFAULT_FORMAT: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+const ISO_8601_FORMATS: [(&str, &str); 5] = [
+    ("date", "%Y-%m-%d"),
+    ("hours", "%Y-%m-%dT%H%:z"),
+    ("minutes", "%Y-%m-%dT%H:%M%:z"),
+    ("seconds", "%Y-%m-%dT%H:%M:%S%:z"),
+    ("ns", "%Y-%m-%dT%H:%M:%S,%N%:z"),
+];
+
+/// Options whose separate value may start with a hyphen, so it is never read as -I
+const HYPHEN_VALUE_OPTIONS: [&str; 3] = ["-d", "--date", "-v"];
+
+const RFC_3339_FORMATS: [(&str, &str); 3] = [
+    ("date", "%Y-%m-%d"),
+    ("seconds", "%Y-%m-%d %H:%M:%S%:z"),
+    ("ns", "%Y-%m-%d %H:%M:%S.%N%:z"),
+];
+
 #[derive(Parser)]
 struct Cli {
+    /// Output format, starting with + as in date(1)
+    #[clap(conflicts_with = "is-rfc-2822")]
+    format: Option<String>,
+
+    /// Display the time described by STRING instead of now. Months overflow as in date(1), so
+    /// "2024-01-31 1 month" is Mar 2, unlike add and -v, which stop at the end of the month
+    #[clap(short = 'd', long = "date", value_name = "STRING", allow_hyphen_values = true)]
+    date: Option<String>,
+
+    /// Display the time described by each line of FILE, or of stdin when FILE is -
+    #[clap(
+        short = 'f',
+        long = "file",
+        value_name = "FILE",
+        parse(from_os_str),
+        conflicts_with = "date"
+    )]
+    file: Option<PathBuf>,
+
+    /// Display the last modification time of FILE
+    #[clap(
+        short = 'r',
+        long = "reference",
+        value_name = "FILE",
+        parse(from_os_str),
+        conflicts_with_all = &["date", "file"]
+    )]
+    reference: Option<PathBuf>,
+
+    /// Which time of the reference FILE to display
+    #[clap(
+        long = "reference-time",
+        value_name = "TIME",
+        possible_values = &["mtime", "atime", "ctime", "birth"],
+        requires = "reference"
+    )]
+    reference_time: Option<String>,
+
+    /// Display the time of an RFC 868 time server and the local clock's offset from it
+    #[clap(
+        long = "query",
+        value_name = "HOST[:PORT]",
+        conflicts_with_all = &["date", "file", "reference"]
+    )]
+    query: Option<String>,
+
+    /// Display the time of an NTP server with the local clock's offset, the delay and stratum
+    #[clap(
+        long = "ntp",
+        value_name = "HOST[:PORT]",
+        conflicts_with_all = &["date", "file", "reference", "query"]
+    )]
+    ntp: Option<String>,
+
+    /// Seconds to wait for each NTP reply
+    #[clap(
+        long = "timeout",
+        value_name = "SECONDS",
+        default_value = "5",
+        requires = "ntp"
+    )]
+    timeout: f64,
+
+    /// How many more NTP requests to send when one goes unanswered
+    #[clap(
+        long = "retries",
+        value_name = "COUNT",
+        default_value = "3",
+        requires = "ntp"
+    )]
+    retries: u32,
+
+    /// Query the time server over UDP instead of TCP
+    #[clap(long = "udp", action, requires = "query")]
+    is_udp: bool,
+
+    /// Serve the local time over RFC 868 on TCP and UDP
+    #[clap(
+        long = "serve",
+        action,
+        conflicts_with_all = &["date", "file", "reference", "query", "ntp"]
+    )]
+    is_serve: bool,
+
+    /// Port to serve the time on, 37 by default
+    #[clap(long = "port", value_name = "PORT", requires = "is-serve")]
+    port: Option<u16>,
+
+    /// Adjust the date as BSD date does: -v+1d, -v-3H, -v1m to set the month, -vfri; repeatable
+    #[clap(
+        short = 'v',
+        value_name = "[+|-]VAL[ymwdHMS]",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        allow_hyphen_values = true
+    )]
+    adjustments: Vec<String>,
+
     /// Use RFC 2822 date and time output format.
-    #[clap(short = 'R', action)]
+    #[clap(short = 'R', action, global = true)]
     is_rfc_2822: bool,
 
+    /// Use ISO 8601 output format, precise to date (default), hours, minutes, seconds or ns
+    #[clap(
+        short = 'I',
+        long = "iso-8601",
+        value_name = "TIMESPEC",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "date",
+        conflicts_with_all = &["format", "is-rfc-2822", "rfc-3339"]
+    )]
+    iso_8601: Option<String>,
+
+    /// Use RFC 3339 output format, precise to date, seconds or ns
+    #[clap(
+        long = "rfc-3339",
+        value_name = "TIMESPEC",
+        conflicts_with_all = &["format", "is-rfc-2822"]
+    )]
+    rfc_3339: Option<String>,
+
+    /// Display day and month names and %c, %x and %X in LOCALE, such as fr_FR, instead of LC_TIME
+    #[clap(long = "locale", value_name = "LOCALE", global = true)]
+    locale: Option<String>,
+
     /// Display the date in UTC (Coordinated Universal) time.
-    #[clap(short = 'u', action)]
+    #[clap(short = 'u', action, global = true)]
     is_utc: bool,
+
+    /// Display the date in the time zone ZONE, such as Europe/Paris, instead of TZ
+    #[clap(
+        long = "tz",
+        value_name = "ZONE",
+        global = true,
+        conflicts_with = "is-utc"
+    )]
+    tz: Option<String>,
+
+    /// Use TIMESTAMP, in seconds since the epoch or as an absolute -d date, as the current time
+    #[clap(
+        long = "now",
+        value_name = "TIMESTAMP",
+        hide = true,
+        global = true,
+        allow_hyphen_values = true
+    )]
+    now: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Display the time from START to END, both parsed as -d does
+    Diff {
+        start: String,
+
+        end: String,
+
+        /// Display the difference in seconds, as an ISO 8601 duration or in words
+        #[clap(
+            short = 'o',
+            long = "output",
+            value_name = "UNITS",
+            possible_values = &["seconds", "iso-8601", "human"],
+            default_value = "seconds"
+        )]
+        output: String,
+    },
+
+    /// Display DATE moved by DURATION, such as "90 days", -1h30m or P1M2D
+    ///
+    /// Months keep the day of the month, or stop at the end of a shorter month as -v does, so
+    /// 2024-01-31 plus 1 month is Feb 29. -d "2024-01-31 1 month" overflows to Mar 2 instead, as
+    /// in date(1).
+    Add {
+        date: String,
+
+        #[clap(allow_hyphen_values = true)]
+        duration: String,
+
+        /// Output format, starting with + as in date(1)
+        #[clap(conflicts_with = "is-rfc-2822")]
+        format: Option<String>,
+    },
+
+    /// Display a calendar as cal(1) does, for this month, MONTH YEAR or the whole of YEAR
+    Cal {
+        #[clap(value_name = "[MONTH] YEAR", max_values = 2)]
+        operands: Vec<String>,
+
+        /// Display the previous, current and next month
+        #[clap(short = '3', action, conflicts_with = "is-year")]
+        is_three_months: bool,
+
+        /// Display the whole year
+        #[clap(short = 'y', action)]
+        is_year: bool,
+
+        /// Start weeks on Monday
+        #[clap(short = 'm', action)]
+        is_monday_first: bool,
+
+        /// Number days from the start of the year
+        #[clap(short = 'j', action)]
+        is_julian: bool,
+
+        /// Display ISO 8601 week numbers
+        #[clap(short = 'w', action)]
+        is_weeks: bool,
+    },
+
+    /// Display the elapsed time, with a lap each time Enter is pressed, until the end of input
+    Stopwatch,
+
+    /// Count down DURATION, such as 5m, 1h30m or PT90S, and exit at zero
+    Countdown {
+        #[clap(allow_hyphen_values = true)]
+        duration: String,
+    },
+
+    /// Count down to DATE, parsed as -d does, and exit at zero
+    Until { date: String },
 }
 
 #[derive(Debug, PartialEq)]
-enum CustomError {}
+#[allow(clippy::enum_variant_names)]
+enum CustomError {
+    InvalidFormat(String),
+    InvalidOperand(String),
+    InvalidDate(String),
+    InvalidLine(usize, String),
+    InvalidPath(String),
+    ReadFailed(String),
+    InvalidTimeZone(String),
+    InvalidTimespec(String),
+    TimeUnavailable(String, String),
+    InvalidAdjustment(String),
+    InvalidDuration(String),
+    InvalidHost(String),
+    QueryFailed(String),
+    BindFailed(String),
+    QueryRefused(String, String),
+    InvalidTimeout(String),
+    InvalidLocale(String),
+    InvalidSourceDateEpoch(String),
+    InvalidMonth(String),
+    InvalidYear(String),
+    WriteFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileTime {
+    Modified,
+    Accessed,
+    Changed,
+    Birth,
+}
+
+/// Where the displayed date comes from, other than the lines of -f
+#[derive(Debug, PartialEq, Eq)]
+enum DateSource {
+    Now,
+    Expression(String),
+    Reference(PathBuf, FileTime),
+}
+
+/// How many months cal displays around the chosen one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CalendarView {
+    Month,
+    ThreeMonths,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOutput {
+    Seconds,
+    Iso8601,
+    Human,
+}
 
 fn main() -> Result<(), CustomError> {
-    let args = Cli::parse();
+    let args = Cli::parse_from(process_argv(std::env::args_os()));
+
+    let tz = args.tz.or_else(|| std::env::var("TZ").ok());
+
+    let operand = match &args.command {
+        Some(Command::Add { format, .. }) => format.as_deref().or(args.format.as_deref()),
+        _ => args.format.as_deref(),
+    };
+    let format = process_format(operand, args.iso_8601.as_deref(), args.rfc_3339.as_deref());
+
+    // LC_ALL Overrides LC_TIME, Which Overrides LANG, As In setlocale(3)
+    let environment_locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+
+    // Only --locale Can Fail, As An Unknown Locale In The Environment Leaves setlocale(3) At POSIX
+    let locale = match args.locale {
+        Some(locale) => process_locale(Some(&locale)),
+        None => Ok(process_locale(environment_locale.as_deref()).unwrap_or(chrono::Locale::POSIX)),
+    };
+
+    let source = match (args.date, args.reference) {
+        (Some(date), _) => DateSource::Expression(date),
+        (None, Some(path)) => DateSource::Reference(path, parse_file_time(args.reference_time)),
+        (None, None) => DateSource::Now,
+    };
 
-    let result = process_date_handler(args.is_rfc_2822, args.is_utc);
-    match result {
-        Ok(result) => println!("{}", result),
+    // SOURCE_DATE_EPOCH Fixes The Date Displayed, Never A Clock Compared With A Time Server Or
+    // Counted Down In Real Time
+    let is_measured = args.is_serve
+        || match &args.command {
+            Some(Command::Countdown { .. } | Command::Until { .. }) => true,
+            Some(_) => false,
+            None => args.query.is_some() || args.ntp.is_some(),
+        };
+
+    // --now Wins Over SOURCE_DATE_EPOCH, So A Reproducible Build Can Still Be Tested
+    let source_date_epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .filter(|_| !is_measured);
+    let clock = process_clock(args.now.as_deref(), source_date_epoch.as_deref());
+
+    let output = format.and_then(|format| {
+        let locale = locale?;
+        let clock = clock?;
+
+        if args.is_serve {
+            return process_serve_handler(args.port.unwrap_or(time_protocol::PORT), clock)
+                .map(|_| None);
+        }
+        let zone = process_zone(args.is_utc, tz.as_deref())?;
+
+        match (&args.command, &args.query, &args.file) {
+            (Some(Command::Diff { start, end, output }), _, _) => {
+                process_diff_handler(start, end, parse_diff_output(output), zone, clock).map(Some)
+            }
+            (
+                Some(Command::Cal {
+                    operands,
+                    is_three_months,
+                    is_year,
+                    is_monday_first,
+                    is_julian,
+                    is_weeks,
+                }),
+                _,
+                _,
+            ) => {
+                let view = match (is_three_months, is_year) {
+                    (_, true) => CalendarView::Year,
+                    (true, _) => CalendarView::ThreeMonths,
+                    _ => CalendarView::Month,
+                };
+                let layout = calendar::Layout {
+                    is_monday_first: *is_monday_first,
+                    is_julian: *is_julian,
+                    is_weeks: *is_weeks,
+                };
+                process_cal_handler(operands, view, layout, locale, zone, clock).map(Some)
+            }
+            (Some(Command::Stopwatch), _, _) => process_stopwatch_handler().map(|_| None),
+            (Some(Command::Countdown { duration }), _, _) => {
+                let now = clock.now().with_timezone(&zone);
+                process_countdown(duration, now)
+                    .map(process_timer_handler)
+                    .map(|_| None)
+            }
+            (Some(Command::Until { date }), _, _) => {
+                let now = clock.now().with_timezone(&zone);
+                process_until(date, now)
+                    .map(process_timer_handler)
+                    .map(|_| None)
+            }
+            (Some(Command::Add { date, duration, .. }), _, _) => process_add_handler(
+                args.is_rfc_2822,
+                format,
+                locale,
+                date,
+                duration,
+                zone,
+                clock,
+            )
+            .map(Some),
+            (None, _, _) if args.ntp.is_some() => process_ntp_handler(
+                args.is_rfc_2822,
+                format,
+                locale,
+                args.ntp.as_deref().unwrap_or_default(),
+                args.timeout,
+                args.retries,
+                zone,
+                clock,
+            )
+            .map(Some),
+            (None, Some(host), _) => process_query_handler(
+                args.is_rfc_2822,
+                format,
+                locale,
+                host,
+                args.is_udp,
+                zone,
+                clock,
+            )
+            .map(Some),
+            (None, None, Some(path)) => process_file_handler(
+                args.is_rfc_2822,
+                format,
+                locale,
+                path,
+                &args.adjustments,
+                zone,
+                clock,
+            )
+            .map(|_| None),
+            (None, None, None) => process_date_handler(
+                args.is_rfc_2822,
+                format,
+                locale,
+                source,
+                &args.adjustments,
+                zone,
+                clock,
+            )
+            .map(Some),
+        }
+    });
+
+    match output {
+        Ok(Some(output)) => println!("{}", output),
+        Ok(None) => (),
         Err(error) => eprintln!("Error: {}", parse_custom_error(error)),
     }
 
     Ok(())
 }
 
+/// Rewrites -I<TIMESPEC> as --iso-8601=<TIMESPEC>, as clap only takes an optional value after =.
+/// A bare -I then never takes the next word, such as a subcommand, as its TIMESPEC.
+fn process_argv(argv: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut result = vec![];
+    let mut is_value = false;
+    let mut is_operand = false;
+
+    for arg in argv {
+        let text = arg.to_str();
+        let split = match is_value || is_operand {
+            true => None,
+            false => text.and_then(split_iso_8601),
+        };
+
+        is_value = !is_value && text.is_some_and(|text| HYPHEN_VALUE_OPTIONS.contains(&text));
+        is_operand |= text == Some("--");
+
+        match split {
+            Some(args) => result.extend(args),
+            None => result.push(arg),
+        }
+    }
+
+    result
+}
+
+/// Splits -Iseconds, or a cluster of flags ending in it such as -uIseconds
+fn split_iso_8601(arg: &str) -> Option<Vec<OsString>> {
+    let cluster = arg
+        .strip_prefix('-')
+        .filter(|cluster| !cluster.starts_with('-'))?;
+    let (flags, timespec) = cluster.split_once('I')?;
+
+    let is_flags = flags.chars().all(|flag| matches!(flag, 'R' | 'u'));
+    if !is_flags || timespec.is_empty() || timespec.starts_with('=') {
+        return None;
+    }
+
+    let mut args = vec![];
+    if !flags.is_empty() {
+        args.push(OsString::from(format!("-{}", flags)));
+    }
+    args.push(OsString::from(format!("--iso-8601={}", timespec)));
+
+    Some(args)
+}
+
 fn parse_custom_error(error: CustomError) -> String {
-    match error {}
+    match error {
+        CustomError::InvalidFormat(spec) => format!("invalid format specifier provided: {}", spec),
+        CustomError::InvalidOperand(operand) => format!(
+            "invalid operand provided: {} (formats start with +)",
+            operand
+        ),
+        CustomError::InvalidDate(date) => format!("invalid date provided: {}", date),
+        CustomError::InvalidLine(number, date) => {
+            format!("line {}: invalid date provided: {}", number, date)
+        }
+        CustomError::InvalidPath(path) => format!("invalid filepath provided: {}", path),
+        CustomError::ReadFailed(path) => format!("failed to read: {}", path),
+        CustomError::InvalidTimeZone(tz) => format!(
+            "invalid time zone provided: {} (expected a name such as Europe/Paris)",
+            tz
+        ),
+        CustomError::InvalidTimespec(timespec) => {
+            format!("invalid timespec provided: {}", timespec)
+        }
+        CustomError::TimeUnavailable(time, path) => {
+            format!("{} is not reported for: {}", time, path)
+        }
+        CustomError::InvalidAdjustment(adjustment) => {
+            format!("invalid adjustment provided: {}", adjustment)
+        }
+        CustomError::InvalidDuration(duration) => {
+            format!("invalid duration provided: {}", duration)
+        }
+        CustomError::InvalidHost(host) => format!("invalid host provided: {}", host),
+        CustomError::QueryFailed(host) => format!("no time received from: {}", host),
+        CustomError::BindFailed(address) => format!("failed to listen on: {}", address),
+        CustomError::QueryRefused(host, reason) => {
+            format!("time refused by: {} ({})", host, reason)
+        }
+        CustomError::InvalidTimeout(timeout) => format!("invalid timeout provided: {}", timeout),
+        CustomError::InvalidLocale(locale) => format!(
+            "invalid locale provided: {} (expected a name such as fr_FR)",
+            locale
+        ),
+        CustomError::InvalidSourceDateEpoch(epoch) => format!(
+            "invalid SOURCE_DATE_EPOCH provided: {} (expected seconds since the epoch)",
+            epoch
+        ),
+        CustomError::InvalidMonth(month) => format!(
+            "invalid month provided: {} (expected 1 to 12 or a name such as March)",
+            month
+        ),
+        CustomError::InvalidYear(year) => {
+            format!("invalid year provided: {} (expected 1 to 9999)", year)
+        }
+        CustomError::WriteFailed => "failed to write output".to_string(),
+    }
+}
+
+fn process_date_handler(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    source: DateSource,
+    adjustments: &[String],
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let now = clock.now().with_timezone(&zone);
+
+    // A Reference File Stands In For Now, So Adjustments Apply To It The Same Way
+    let (date, now) = match source {
+        DateSource::Now => (None, now),
+        DateSource::Expression(date) => (Some(date), now),
+        DateSource::Reference(path, file_time) => {
+            let reference = process_reference(&path, file_time)?;
+            (None, reference.with_timezone(&zone))
+        }
+    };
+
+    process_date_at(
+        is_rfc_2822,
+        format,
+        locale,
+        date.as_deref(),
+        adjustments,
+        now,
+    )
+}
+
+fn process_diff_handler(
+    start: &str,
+    end: &str,
+    output: DiffOutput,
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let now = clock.now().with_timezone(&zone);
+
+    process_diff(start, end, output, now)
+}
+
+fn parse_diff_output(output: &str) -> DiffOutput {
+    match output {
+        "iso-8601" => DiffOutput::Iso8601,
+        "human" => DiffOutput::Human,
+        _ => DiffOutput::Seconds,
+    }
+}
+
+/// The time from `start` to `end`, negative when `end` is earlier. Months and days are
+/// counted in local time, so a day across a DST change is still one day.
+fn process_diff<T: chrono::TimeZone>(
+    start: &str,
+    end: &str,
+    output: DiffOutput,
+    now: chrono::DateTime<T>,
+) -> Result<String, CustomError> {
+    let start = parse::parse_date(start, &now)?;
+    let end_date = parse::parse_date(end, &now)?;
+
+    if output == DiffOutput::Seconds {
+        let elapsed = end_date.signed_duration_since(start);
+        return Ok(duration::format_seconds(elapsed));
+    }
+
+    let span = duration::between(&start, &end_date)
+        .ok_or_else(|| CustomError::InvalidDate(end.to_string()))?;
+    match output {
+        DiffOutput::Human => Ok(duration::format_human(&span)),
+        _ => Ok(duration::format_iso_8601(&span)),
+    }
+}
+
+fn process_add_handler(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    date: &str,
+    duration: &str,
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let now = clock.now().with_timezone(&zone);
+
+    process_add(is_rfc_2822, format, locale, date, duration, now)
+}
+
+/// Formats `date` moved by `duration`, where months clamp to the end of the month
+fn process_add<T: chrono::TimeZone>(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    date: &str,
+    duration: &str,
+    now: chrono::DateTime<T>,
+) -> Result<String, CustomError>
+where
+    T::Offset: std::fmt::Display,
+{
+    let span = duration::parse_duration(duration)?;
+    let date = parse::parse_date(date, &now)?;
+    let date = duration::add_span(&date, &span)
+        .ok_or_else(|| CustomError::InvalidDuration(duration.to_string()))?;
+
+    process_date(is_rfc_2822, format, locale, date)
+}
+
+fn process_cal_handler(
+    operands: &[String],
+    view: CalendarView,
+    layout: calendar::Layout,
+    locale: chrono::Locale,
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let today = clock.now().with_timezone(&zone).naive_local().date();
+    let is_highlighted = atty::is(atty::Stream::Stdout);
+
+    process_cal(operands, view, layout, locale, today, is_highlighted)
+}
+
+/// A lone YEAR displays the whole year, as in cal(1)
+fn process_cal(
+    operands: &[String],
+    view: CalendarView,
+    layout: calendar::Layout,
+    locale: chrono::Locale,
+    today: chrono::NaiveDate,
+    is_highlighted: bool,
+) -> Result<String, CustomError> {
+    let (month, year, view) = match operands {
+        [month, year] => (
+            calendar::parse_month(month)?,
+            calendar::parse_year(year)?,
+            view,
+        ),
+        [year] => (1, calendar::parse_year(year)?, CalendarView::Year),
+        _ => (
+            chrono::Datelike::month(&today),
+            chrono::Datelike::year(&today),
+            view,
+        ),
+    };
+    let highlighted = Some(today).filter(|_| is_highlighted);
+
+    let lines = match view {
+        CalendarView::Year => calendar::year_lines(year, layout, locale, highlighted),
+        CalendarView::ThreeMonths => {
+            // Months Before Year 1 Or After 9999 Are Left Out
+            let months = (-1..=1)
+                .filter_map(|offset| {
+                    let index = year * 12 + month as i32 - 1 + offset;
+                    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+                    (1..=9999).contains(&year).then(|| {
+                        calendar::month_lines(year, month, true, layout, locale, highlighted)
+                    })
+                })
+                .collect::<Vec<Vec<String>>>();
+            calendar::join_months(&months)
+        }
+        CalendarView::Month => calendar::join_months(&[calendar::month_lines(
+            year,
+            month,
+            true,
+            layout,
+            locale,
+            highlighted,
+        )]),
+    };
+
+    Ok(lines.join("\n").trim_end().to_string())
+}
+
+/// Laps come from lines on stdin, read on their own thread so the time keeps moving
+fn process_stopwatch_handler() -> Result<(), CustomError> {
+    let (sender, laps) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for _ in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut display = timer::Display {
+        output: io::stdout().lock(),
+        is_live: atty::is(atty::Stream::Stdout),
+        is_echoed: atty::is(atty::Stream::Stdin),
+    };
+
+    // A Closed Output Ends The Timer Early
+    let _ = timer::stopwatch(&laps, &mut display, &mut timer::SystemTicker::start());
+    Ok(())
+}
+
+fn process_timer_handler(total: chrono::Duration) {
+    let mut display = timer::Display {
+        output: io::stdout().lock(),
+        is_live: atty::is(atty::Stream::Stdout),
+        is_echoed: false,
+    };
+
+    // A Closed Output Ends The Timer Early
+    let _ = timer::countdown(total, &mut display, &mut timer::SystemTicker::start());
+}
+
+/// The time until `now` moved by `duration`, so "1 day" is a calendar day across a DST change
+fn process_countdown<T: chrono::TimeZone>(
+    duration: &str,
+    now: chrono::DateTime<T>,
+) -> Result<chrono::Duration, CustomError> {
+    let invalid = || CustomError::InvalidDuration(duration.to_string());
+    let span = duration::parse_duration(duration)?;
+    if span.is_negative() {
+        return Err(invalid());
+    }
+
+    let end = duration::add_span(&now, &span).ok_or_else(invalid)?;
+    Ok(end.signed_duration_since(now))
+}
+
+/// The time until `date`, which is nothing when it has already passed
+fn process_until<T: chrono::TimeZone>(
+    date: &str,
+    now: chrono::DateTime<T>,
+) -> Result<chrono::Duration, CustomError> {
+    let end = parse::parse_date(date, &now)?;
+
+    Ok(end.signed_duration_since(now).max(chrono::Duration::zero()))
+}
+
+fn process_query_handler(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    host: &str,
+    is_udp: bool,
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let transport = match is_udp {
+        true => time_protocol::Transport::Udp,
+        false => time_protocol::Transport::Tcp,
+    };
+    let (remote, offset) = time_protocol::query(host, transport, clock)?;
+
+    process_query(
+        is_rfc_2822,
+        format,
+        locale,
+        remote.with_timezone(&zone),
+        offset,
+    )
+}
+
+/// The remote time, then how far it is ahead of the local clock in whole seconds, as the protocol
+/// cannot measure it any finer
+fn process_query<T: chrono::TimeZone>(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    remote: chrono::DateTime<T>,
+    offset: chrono::Duration,
+) -> Result<String, CustomError>
+where
+    T::Offset: std::fmt::Display,
+{
+    let date = process_date(is_rfc_2822, format, locale, remote)?;
+    let offset = chrono::Duration::seconds(offset.num_seconds());
+
+    Ok(format!(
+        "{}\noffset {} seconds",
+        date,
+        duration::format_signed_seconds(offset)
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_ntp_handler(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    host: &str,
+    timeout: f64,
+    retries: u32,
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<String, CustomError> {
+    let timeout = process_timeout(timeout)?;
+    let sample = ntp::query(host, timeout, retries, clock)?;
+
+    process_ntp(is_rfc_2822, format, locale, &sample, &zone)
+}
+
+fn process_timeout(timeout: f64) -> Result<std::time::Duration, CustomError> {
+    match timeout > 0.0 && timeout.is_finite() {
+        true => Ok(
+            std::time::Duration::from_secs_f64(timeout).max(std::time::Duration::from_millis(1))
+        ),
+        false => Err(CustomError::InvalidTimeout(timeout.to_string())),
+    }
+}
+
+/// The server's time, then the local clock's offset from it and the round trip delay to the
+/// microsecond, and the server's stratum
+fn process_ntp<T: chrono::TimeZone>(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    sample: &ntp::Sample,
+    zone: &T,
+) -> Result<String, CustomError>
+where
+    T::Offset: std::fmt::Display,
+{
+    let date = process_date(is_rfc_2822, format, locale, sample.time.with_timezone(zone))?;
+    let microseconds = |duration: chrono::Duration| {
+        chrono::Duration::microseconds(duration.num_microseconds().unwrap_or(i64::MAX))
+    };
+
+    Ok(format!(
+        "{}\noffset {} seconds\ndelay {} seconds\nstratum {}",
+        date,
+        duration::format_signed_seconds(microseconds(sample.offset)),
+        duration::format_seconds(microseconds(sample.delay)),
+        sample.stratum
+    ))
+}
+
+fn process_serve_handler(port: u16, clock: clock::Clock) -> Result<(), CustomError> {
+    time_protocol::serve(port, clock)
+}
+
+fn parse_file_time(reference_time: Option<String>) -> FileTime {
+    match reference_time.as_deref() {
+        Some("atime") => FileTime::Accessed,
+        Some("ctime") => FileTime::Changed,
+        Some("birth") => FileTime::Birth,
+        _ => FileTime::Modified,
+    }
+}
+
+/// Reads one of the timestamps of `path`, where birth time depends on the filesystem
+fn process_reference(
+    path: &Path,
+    file_time: FileTime,
+) -> Result<chrono::DateTime<chrono::Utc>, CustomError> {
+    let path_name = path.display().to_string();
+    let metadata = std::fs::metadata(path).map_err(|error| match error.kind() {
+        io::ErrorKind::NotFound => CustomError::InvalidPath(path_name.clone()),
+        _ => CustomError::ReadFailed(path_name.clone()),
+    })?;
+
+    let time = match file_time {
+        FileTime::Modified => metadata.modified(),
+        FileTime::Accessed => metadata.accessed(),
+        FileTime::Birth => metadata.created(),
+        FileTime::Changed => {
+            let nanoseconds = metadata.ctime_nsec() as u32;
+            return chrono::TimeZone::timestamp_opt(&chrono::Utc, metadata.ctime(), nanoseconds)
+                .single()
+                .ok_or_else(|| CustomError::TimeUnavailable("ctime".to_string(), path_name));
+        }
+    };
+
+    let name = match file_time {
+        FileTime::Accessed => "atime",
+        FileTime::Birth => "birth time",
+        _ => "mtime",
+    };
+    time.map(chrono::DateTime::from)
+        .map_err(|_| CustomError::TimeUnavailable(name.to_string(), path_name))
+}
+
+fn process_file_handler(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    path: &Path,
+    adjustments: &[String],
+    zone: zone::Zone,
+    clock: clock::Clock,
+) -> Result<(), CustomError> {
+    let reader: Box<dyn BufRead> = match path.to_str() {
+        Some("-") => Box::new(io::stdin().lock()),
+        _ => File::open(path)
+            .map(|file| Box::new(BufReader::new(file)) as Box<dyn BufRead>)
+            .map_err(|_| CustomError::InvalidPath(path.display().to_string()))?,
+    };
+
+    let lines = reader
+        .lines()
+        .map(|line| line.map_err(|_| CustomError::ReadFailed(path.display().to_string())));
+
+    let now = clock.now().with_timezone(&zone);
+
+    process_lines(
+        is_rfc_2822,
+        format,
+        locale,
+        lines,
+        adjustments,
+        now,
+        &mut io::stdout(),
+        &mut io::stderr(),
+    )
 }
 
-fn process_date_handler(is_rfc_2822: bool, is_utc: bool) -> Result<String, CustomError> {
+/// -u wins over TZ, which falls back to the system zone in /etc/localtime
+fn process_zone(is_utc: bool, tz: Option<&str>) -> Result<zone::Zone, CustomError> {
     match is_utc {
-        true => process_date::<chrono::Utc>(is_rfc_2822, chrono::offset::Utc::now()),
-        false => process_date::<chrono::Local>(is_rfc_2822, chrono::offset::Local::now()),
+        true => Ok(zone::Zone::utc()),
+        false => zone::Zone::load(tz),
     }
 }
 
-fn process_date<T: chrono::TimeZone>(
+/// Formats every non-blank line as its own date expression, writing each as soon as it is read
+/// so a never ending stream such as tail -f works. Lines that do not parse are written to
+/// `errors` with their line number, anything else aborts the rest of the stream.
+#[allow(clippy::too_many_arguments)]
+fn process_lines<T: chrono::TimeZone, W: Write, E: Write>(
     is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    lines: impl IntoIterator<Item = Result<String, CustomError>>,
+    adjustments: &[String],
+    now: chrono::DateTime<T>,
+    writer: &mut W,
+    errors: &mut E,
+) -> Result<(), CustomError>
+where
+    T::Offset: std::fmt::Display,
+{
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let written = match process_date_at(
+            is_rfc_2822,
+            format,
+            locale,
+            Some(&line),
+            adjustments,
+            now.clone(),
+        ) {
+            Err(CustomError::InvalidDate(date)) => {
+                let error = CustomError::InvalidLine(index + 1, date);
+                writeln!(errors, "Error: {}", parse_custom_error(error))
+            }
+            Err(error) => return Err(error),
+            Ok(result) => writeln!(writer, "{}", result),
+        };
+        written.map_err(|_| CustomError::WriteFailed)?;
+    }
+
+    Ok(())
+}
+
+/// Formats `date` when one is given, parsed relative to `now`, otherwise `now` itself,
+/// after applying the -v adjustments in order
+fn process_date_at<T: chrono::TimeZone>(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    date: Option<&str>,
+    adjustments: &[String],
     now: chrono::DateTime<T>,
 ) -> Result<String, CustomError>
 where
     T::Offset: std::fmt::Display,
 {
-    if is_rfc_2822 {
-        return Ok(now.to_rfc2822());
+    let date = match date {
+        Some(date) => parse::parse_date(date, &now)?,
+        None => now,
+    };
+    let date = adjust::adjust_date(date, adjustments)?;
+
+    process_date(is_rfc_2822, format, locale, date)
+}
+
+/// Picks the strftime format for a +FORMAT operand, -I or --rfc-3339, defaulting to date(1)'s
+fn process_format<'a>(
+    format: Option<&'a str>,
+    iso_8601: Option<&str>,
+    rfc_3339: Option<&str>,
+) -> Result<&'a str, CustomError> {
+    match (format, iso_8601, rfc_3339) {
+        (Some(format), _, _) => parse_format(format),
+        (None, Some(timespec), _) => parse_timespec(timespec, &ISO_8601_FORMATS),
+        (None, None, Some(timespec)) => parse_timespec(timespec, &RFC_3339_FORMATS),
+        (None, None, None) => Ok(DEFAULT_FORMAT),
     }
+}
+
+/// Accepts names as LANG has them, such as fr_FR.UTF-8 or be_BY@latin, where C and POSIX are
+/// the default English names
+fn process_locale(locale: Option<&str>) -> Result<chrono::Locale, CustomError> {
+    let locale = match locale {
+        Some(locale) => locale,
+        None => return Ok(chrono::Locale::POSIX),
+    };
+
+    let (name, modifier) = match locale.split_once('@') {
+        Some((name, modifier)) => (name, Some(modifier)),
+        None => (locale, None),
+    };
+    let name = name.split_once('.').map_or(name, |(name, _)| name);
+
+    // A Modifier The Locale Does Not Have, Such As @euro, Falls Back To The Plain Name
+    let modified = modifier.map(|modifier| format!("{}@{}", name, modifier));
+    match name {
+        "C" | "POSIX" => Ok(chrono::Locale::POSIX),
+        _ => modified
+            .and_then(|modified| chrono::Locale::try_from(modified.as_str()).ok())
+            .or_else(|| chrono::Locale::try_from(name).ok())
+            .ok_or_else(|| CustomError::InvalidLocale(locale.to_string())),
+    }
+}
+
+/// A fixed clock from --now or SOURCE_DATE_EPOCH, or the system clock. --now takes seconds
+/// since the epoch or any -d date, read in UTC unless it has an offset.
+fn process_clock(
+    now: Option<&str>,
+    source_date_epoch: Option<&str>,
+) -> Result<clock::Clock, CustomError> {
+    if let Some(now) = now {
+        let is_timestamp = now.parse::<f64>().is_ok() && !now.contains(char::is_alphabetic);
+        let expression = match is_timestamp {
+            true => format!("@{}", now),
+            false => now.to_string(),
+        };
+        let fixed = parse::parse_date(&expression, &chrono::offset::Utc::now())
+            .map_err(|_| CustomError::InvalidDate(now.to_string()))?;
+
+        return Ok(clock::Clock::Fixed(fixed));
+    }
+
+    // The Specification Asks For An Empty Value To Be Ignored
+    match source_date_epoch.filter(|epoch| !epoch.is_empty()) {
+        Some(epoch) => epoch
+            .parse::<i64>()
+            .ok()
+            .filter(|_| epoch.bytes().all(|byte| byte.is_ascii_digit()))
+            .and_then(|seconds| {
+                chrono::TimeZone::timestamp_opt(&chrono::offset::Utc, seconds, 0).single()
+            })
+            .map(clock::Clock::Fixed)
+            .ok_or_else(|| CustomError::InvalidSourceDateEpoch(epoch.to_string())),
+        None => Ok(clock::Clock::System),
+    }
+}
+
+/// Accepts any unambiguous prefix of a timespec, so -Is is -Iseconds as in GNU date
+fn parse_timespec(
+    timespec: &str,
+    formats: &[(&str, &'static str)],
+) -> Result<&'static str, CustomError> {
+    let mut matches = formats
+        .iter()
+        .filter(|(name, _)| !timespec.is_empty() && name.starts_with(timespec));
 
-    let format_specifier = "%a %b %e %H:%M:%S %Z %G";
-    let date = now.format(format_specifier);
+    match (matches.next(), matches.next()) {
+        (Some(&(_, format)), None) => Ok(format),
+        _ => Err(CustomError::InvalidTimespec(timespec.to_string())),
+    }
+}
+
+fn parse_format(operand: &str) -> Result<&str, CustomError> {
+    operand
+        .strip_prefix('+')
+        .ok_or_else(|| CustomError::InvalidOperand(operand.to_string()))
+}
+
+fn process_date<T: chrono::TimeZone>(
+    is_rfc_2822: bool,
+    format: &str,
+    locale: chrono::Locale,
+    date: chrono::DateTime<T>,
+) -> Result<String, CustomError>
+where
+    T::Offset: std::fmt::Display,
+{
+    if is_rfc_2822 {
+        return Ok(date.to_rfc2822());
+    }
 
-    Ok(date.to_string())
+    format::format_date(&date, format, locale)
 }
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Duration, Utc};
+use std::net::UdpSocket;
+use std::time::Instant;
+
+use crate::clock::Clock;
+use crate::time_protocol;
+use crate::CustomError;
+
+/// The well known port of NTP
+pub const PORT: u16 = 123;
+
+const PACKET_SIZE: usize = 48;
+
+/// Leap indicator 0, version 4 and mode 3, a client request
+const CLIENT_REQUEST: u8 = 0b00_100_011;
+
+const MODE_SERVER: u8 = 4;
+
+/// Leap indicator 3 means the server's clock is not synchronized
+const LEAP_ALARM: u8 = 3;
+
+/// What a single request measured, with the server's time estimated at the moment it arrived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub time: DateTime<Utc>,
+    pub offset: Duration,
+    pub delay: Duration,
+    pub stratum: u8,
+}
+
+/// What came back from the server for our request
+enum Reply {
+    Sample(Sample),
+    Refused(String),
+}
+
+/// The 64 bit NTP timestamp: seconds since 1900 and a 32 bit binary fraction of a second
+pub fn encode_timestamp(date: &DateTime<Utc>) -> [u8; 8] {
+    let fraction = ((date.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&time_protocol::encode(date));
+    bytes[4..].copy_from_slice(&(fraction as u32).to_be_bytes());
+    bytes
+}
+
+/// Reads times from 1968 to 2104, as the seconds wrap the same way as RFC 868's
+pub fn decode_timestamp(bytes: &[u8]) -> Option<DateTime<Utc>> {
+    let seconds = time_protocol::decode(bytes.get(..4)?.try_into().ok()?)?;
+    let fraction = u32::from_be_bytes(bytes.get(4..8)?.try_into().ok()?) as u64;
+    let nanoseconds = (fraction * 1_000_000_000 + (1 << 31)) >> 32;
+
+    seconds.checked_add_signed(Duration::nanoseconds(nanoseconds as i64))
+}
+
+/// Sends SNTPv4 requests to `host` until one is answered, making `retries` more attempts after
+/// the first, each waiting up to `timeout` for the reply
+pub fn query(
+    host: &str,
+    timeout: std::time::Duration,
+    retries: u32,
+    clock: Clock,
+) -> Result<Sample, CustomError> {
+    let addresses = time_protocol::resolve(host, PORT)?;
+    let failed = || CustomError::QueryFailed(host.to_string());
+
+    let socket = addresses
+        .iter()
+        .find_map(|address| time_protocol::connect_udp(address).ok())
+        .ok_or_else(failed)?;
+
+    for _ in 0..=retries {
+        match exchange(&socket, timeout, clock) {
+            Some(Reply::Sample(sample)) => return Ok(sample),
+            Some(Reply::Refused(reason)) => {
+                return Err(CustomError::QueryRefused(host.to_string(), reason))
+            }
+            None => continue,
+        }
+    }
+
+    Err(failed())
+}
+
+/// One request and its reply, or `None` when nothing valid came back in time. Replies that do
+/// not echo our transmit time are stale or forged and are skipped.
+fn exchange(socket: &UdpSocket, timeout: std::time::Duration, clock: Clock) -> Option<Reply> {
+    let deadline = Instant::now() + timeout;
+
+    let sent = clock.now();
+    let mut request = [0; PACKET_SIZE];
+    request[0] = CLIENT_REQUEST;
+    request[40..48].copy_from_slice(&encode_timestamp(&sent));
+    socket.send(&request).ok()?;
+
+    // Room For The Extension Fields And MAC A Server May Append
+    let mut buffer = [0; 1024];
+    loop {
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())?;
+        socket.set_read_timeout(Some(remaining)).ok()?;
+
+        let length = socket.recv(&mut buffer).ok()?;
+        let received = clock.now();
+
+        let reply = &buffer[..length];
+        let is_ours = length >= PACKET_SIZE
+            && reply[0] & 0b111 == MODE_SERVER
+            && reply[24..32] == request[40..48];
+        if !is_ours {
+            continue;
+        }
+
+        if let Some(reply) = parse_reply(reply, sent, received) {
+            return Some(reply);
+        }
+    }
+}
+
+/// Works out the offset and round trip delay from the four timestamps, as RFC 4330 describes
+fn parse_reply(reply: &[u8], sent: DateTime<Utc>, received: DateTime<Utc>) -> Option<Reply> {
+    let stratum = reply[1];
+
+    // Stratum 0 Is A Kiss-o'-Death, With The Reason As ASCII In The Reference ID
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&reply[12..16]);
+        return Some(Reply::Refused(code.trim_end_matches('\0').to_string()));
+    }
+    if reply[0] >> 6 == LEAP_ALARM {
+        return Some(Reply::Refused("unsynchronized".to_string()));
+    }
+
+    if reply[40..48].iter().all(|&byte| byte == 0) {
+        return None;
+    }
+
+    let server_received = decode_timestamp(&reply[32..40])?;
+    let server_sent = decode_timestamp(&reply[40..48])?;
+
+    let offset = (server_received.signed_duration_since(sent)
+        + server_sent.signed_duration_since(received))
+        / 2;
+    let delay =
+        received.signed_duration_since(sent) - server_sent.signed_duration_since(server_received);
+
+    Some(Reply::Sample(Sample {
+        time: received.checked_add_signed(offset)?,
+        offset,
+        delay,
+        stratum,
+    }))
+}
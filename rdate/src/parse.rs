@@ -0,0 +1,327 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike, Weekday,
+};
+
+use crate::CustomError;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("mon", Weekday::Mon),
+    ("tue", Weekday::Tue),
+    ("wed", Weekday::Wed),
+    ("thu", Weekday::Thu),
+    ("fri", Weekday::Fri),
+    ("sat", Weekday::Sat),
+    ("sun", Weekday::Sun),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Seconds(i64),
+    Days(i64),
+    Months(i64),
+}
+
+/// The pieces of a date expression, applied to `now` in `evaluate`
+#[derive(Debug, Default)]
+struct Expression {
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    offset: Option<FixedOffset>,
+    weekday: Option<(Weekday, i64)>,
+    months: i64,
+    days: i64,
+    seconds: i64,
+    last_relative: Option<(Unit, i64)>,
+}
+
+impl Expression {
+    fn add_relative(&mut self, unit: Unit, amount: i64) {
+        match unit {
+            Unit::Seconds(seconds) => self.seconds += seconds * amount,
+            Unit::Days(days) => self.days += days * amount,
+            Unit::Months(months) => self.months += months * amount,
+        }
+        self.last_relative = Some((unit, amount));
+    }
+}
+
+/// Parses a date expression as `date -d` does: `@EPOCH`, ISO 8601, RFC 2822 or relative phrases
+/// like "2 days ago" and "next friday 14:00", relative to `now` and in its time zone
+pub fn parse_date<T: TimeZone>(value: &str, now: &DateTime<T>) -> Result<DateTime<T>, CustomError> {
+    let value = value.trim();
+    let timezone = now.timezone();
+    let invalid = || CustomError::InvalidDate(value.to_string());
+
+    if let Some(epoch) = value.strip_prefix('@') {
+        return parse_epoch(epoch)
+            .map(|date| date.with_timezone(&timezone))
+            .ok_or_else(invalid);
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&timezone));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Ok(date.with_timezone(&timezone));
+    }
+
+    parse_expression(value)
+        .and_then(|expression| evaluate(&expression, now))
+        .ok_or_else(invalid)
+}
+
+/// Seconds since the epoch, optionally negative or with a fraction
+fn parse_epoch(value: &str) -> Option<DateTime<chrono::Utc>> {
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) || fraction.len() > 9 {
+        return None;
+    }
+
+    let seconds: i64 = seconds.parse().ok()?;
+    let nanoseconds: i64 = format!("{:0<9}", fraction).parse().ok()?;
+    let nanoseconds = if value.starts_with('-') {
+        -nanoseconds
+    } else {
+        nanoseconds
+    };
+
+    chrono::Utc
+        .timestamp_opt(seconds, 0)
+        .single()?
+        .checked_add_signed(Duration::nanoseconds(nanoseconds))
+}
+
+fn parse_expression(value: &str) -> Option<Expression> {
+    let value = value.to_lowercase();
+    let tokens: Vec<&str> = value
+        .split_whitespace()
+        .map(|token| token.trim_end_matches(','))
+        .collect();
+
+    let mut expression = Expression::default();
+    let mut pending: Option<i64> = None;
+
+    for (index, &token) in tokens.iter().enumerate() {
+        let next = tokens.get(index + 1).copied();
+
+        if let Some(unit) = parse_unit(token) {
+            expression.add_relative(unit, pending.take().unwrap_or(1));
+            continue;
+        }
+        if let Some(weekday) = parse_weekday(token) {
+            expression.weekday = Some((weekday, pending.take().unwrap_or(0)));
+            continue;
+        }
+        if pending.is_some() {
+            return None;
+        }
+
+        match token {
+            "now" | "today" => (),
+            "yesterday" => expression.add_relative(Unit::Days(1), -1),
+            "tomorrow" => expression.add_relative(Unit::Days(1), 1),
+            "this" => pending = Some(0),
+            "next" => pending = Some(1),
+            "last" => pending = Some(-1),
+            "ago" => {
+                let (unit, amount) = expression.last_relative.take()?;
+                expression.add_relative(unit, -2 * amount);
+            }
+            "am" | "pm" => expression.time = Some(to_24_hour(expression.time?, token)?),
+            "utc" | "gmt" | "z" => expression.offset = Some(FixedOffset::east(0)),
+            // A Signed Number Right After A Time Of Day Is Its Offset, As In "14:00 +0200"
+            _ if expression.time.is_some()
+                && expression.offset.is_none()
+                && next.and_then(parse_unit).is_none()
+                && parse_offset(token).is_some() =>
+            {
+                expression.offset = parse_offset(token)
+            }
+            _ if token.parse::<i64>().is_ok() => pending = token.parse().ok(),
+            _ => parse_date_time(token, &mut expression)?,
+        }
+    }
+
+    match pending {
+        Some(_) => None,
+        None => Some(expression),
+    }
+}
+
+fn parse_unit(token: &str) -> Option<Unit> {
+    let unit = match token {
+        "sec" | "secs" | "second" | "seconds" => Unit::Seconds(1),
+        "min" | "mins" | "minute" | "minutes" => Unit::Seconds(60),
+        "hour" | "hours" => Unit::Seconds(3600),
+        "day" | "days" => Unit::Days(1),
+        "week" | "weeks" => Unit::Days(7),
+        "fortnight" | "fortnights" => Unit::Days(14),
+        "month" | "months" => Unit::Months(1),
+        "year" | "years" => Unit::Months(12),
+        _ => return None,
+    };
+    Some(unit)
+}
+
+/// Accepts the three letter abbreviation, the full name, and "tues", "wednes" and "thur(s)"
+fn parse_weekday(token: &str) -> Option<Weekday> {
+    let name = token.strip_suffix("day").unwrap_or(token);
+    let name = match name {
+        "tues" => "tue",
+        "wednes" => "wed",
+        "thur" | "thurs" => "thu",
+        "satur" => "sat",
+        _ => name,
+    };
+
+    WEEKDAYS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == name)
+        .map(|&(_, weekday)| weekday)
+}
+
+/// A `YYYY-MM-DD` date, a time of day, or both joined by `T`
+fn parse_date_time(token: &str, expression: &mut Expression) -> Option<()> {
+    let is_time = token.contains(':') || token.ends_with("am") || token.ends_with("pm");
+    let (date, time) = match token.split_once('t') {
+        Some((date, time)) => (Some(date), Some(time)),
+        None if is_time => (None, Some(token)),
+        None => (Some(token), None),
+    };
+
+    if let Some(date) = date {
+        expression.date = Some(NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?);
+    }
+    if let Some(time) = time {
+        let (time, offset) = parse_time(time)?;
+        expression.time = Some(time);
+        expression.offset = offset.or(expression.offset);
+    }
+
+    Some(())
+}
+
+/// `HH:MM[:SS[.fraction]]` or `H[:MM]am`, with an optional `Z` or numeric offset attached
+fn parse_time(token: &str) -> Option<(NaiveTime, Option<FixedOffset>)> {
+    if let Some(time) = token
+        .strip_suffix("am")
+        .or_else(|| token.strip_suffix("pm"))
+    {
+        let suffix = &token[time.len()..];
+        let (hour, minute) = time.split_once(':').unwrap_or((time, "0"));
+        let time = NaiveTime::from_hms_opt(hour.parse().ok()?, minute.parse().ok()?, 0)?;
+        return Some((to_24_hour(time, suffix)?, None));
+    }
+
+    let (time, offset) = match token.find(['+', '-', 'z']) {
+        Some(index) if token[index..] == *"z" => (&token[..index], Some(FixedOffset::east(0))),
+        Some(index) => (&token[..index], Some(parse_offset(&token[index..])?)),
+        None => (token, None),
+    };
+
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+    Some((time, offset))
+}
+
+/// `+HH`, `+HHMM` or `+HH:MM`
+fn parse_offset(token: &str) -> Option<FixedOffset> {
+    let sign = match token.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+
+    let digits = token[1..].replace(':', "");
+    if !digits.bytes().all(|byte| byte.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return None;
+    }
+
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits.get(2..).unwrap_or("0").parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// 12am is midnight and 12pm is noon
+fn to_24_hour(time: NaiveTime, suffix: &str) -> Option<NaiveTime> {
+    let hour = match (time.hour(), suffix) {
+        (0 | 13.., _) => return None,
+        (12, "am") => 0,
+        (12, _) => 12,
+        (hour, "pm") => hour + 12,
+        (hour, _) => hour,
+    };
+    NaiveTime::from_hms_opt(hour, time.minute(), 0)
+}
+
+/// Dates, weekdays, months and days move the calendar in local time, while hours, minutes and
+/// seconds are elapsed time, so "2 hours ago" stays correct across a DST change
+fn evaluate<T: TimeZone>(expression: &Expression, now: &DateTime<T>) -> Option<DateTime<T>> {
+    let local = now.naive_local();
+
+    // A Date Or Weekday Without A Time Of Day Means Midnight
+    let is_day = expression.date.is_some() || expression.weekday.is_some();
+    let time = match (expression.time, is_day) {
+        (Some(time), _) => time,
+        (None, true) => NaiveTime::from_hms(0, 0, 0),
+        (None, false) => local.time(),
+    };
+
+    let mut date = expression.date.unwrap_or_else(|| local.date());
+    if let Some((weekday, ordinal)) = expression.weekday {
+        date = move_to_weekday(date, weekday, ordinal)?;
+    }
+    date = add_months(date, expression.months)?;
+    date = date.checked_add_signed(Duration::days(expression.days))?;
+
+    let local = date.and_time(time);
+    let result = match expression.offset {
+        Some(offset) => offset
+            .from_local_datetime(&local)
+            .single()?
+            .with_timezone(&now.timezone()),
+        None => now.timezone().from_local_datetime(&local).earliest()?,
+    };
+
+    result.checked_add_signed(Duration::seconds(expression.seconds))
+}
+
+/// "friday" and "this friday" include today, "next friday" and "last friday" never do
+fn move_to_weekday(date: NaiveDate, weekday: Weekday, ordinal: i64) -> Option<NaiveDate> {
+    let current = date.weekday().num_days_from_monday() as i64;
+    let target = weekday.num_days_from_monday() as i64;
+
+    let days = match ordinal {
+        0 => (target - current).rem_euclid(7),
+        _ if ordinal > 0 => match (target - current).rem_euclid(7) {
+            0 => 7 * ordinal,
+            days => days + 7 * (ordinal - 1),
+        },
+        _ => match (current - target).rem_euclid(7) {
+            0 => 7 * ordinal,
+            days => -days + 7 * (ordinal + 1),
+        },
+    };
+
+    date.checked_add_signed(Duration::days(days))
+}
+
+/// Adds calendar months, letting a day past the end of the month overflow as date(1) does,
+/// so Jan 31 + 1 month is Mar 2 in a leap year
+pub fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let first = NaiveDate::from_ymd_opt(year, total.rem_euclid(12) as u32 + 1, 1)?;
+
+    first.checked_add_signed(Duration::days(date.day0() as i64))
+}
+
+/// Moves by whole months, keeping the day unless the month is too short for it, so Jan 31 +
+/// 1 month is Feb 29 in a leap year
+pub fn clamp_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let first = add_months(date.with_day(1)?, months)?;
+    let last = add_months(first, 1)?.pred_opt()?;
+
+    first.with_day(date.day().min(last.day()))
+}
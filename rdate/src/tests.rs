@@ -1,16 +1,1068 @@
-// use super::*;
-// use test_case::test_case;
-
-// #[test_case(vec![std::path::PathBuf::from("")],  false, false, "".to_string(), Some(CustomError::ErrInvalidPath); "empty path")]
-// fn test_process_date(
-//     paths: Vec<std::path::PathBuf>,
-//     line_numbers: bool,
-//     non_blank_line_numbers: bool,
-//     expected_result: String,
-//     expected_error: Option<CustomError>,
-// ) {
-//     match expected_error {
-//         None => assert_eq!(expected_result, process_date(paths, line_numbers, non_blank_line_numbers).unwrap()),
-//         Some(expected_error) => assert_eq!(Err(expected_error), process_date(paths, line_numbers, non_blank_line_numbers)),
-//     }
-// }
+use super::*;
+use chrono::{FixedOffset, TimeZone};
+use test_case::test_case;
+
+fn fixed_date(offset: i32) -> chrono::DateTime<FixedOffset> {
+    FixedOffset::east(offset)
+        .ymd(2024, 3, 1)
+        .and_hms_nano(9, 5, 7, 123_456_789)
+}
+
+// Expected outputs match GNU coreutils date
+#[test_case(false, DEFAULT_FORMAT, 7200, "Fri Mar  1 09:05:07 +02:00 2024", None; "default format")]
+#[test_case(true, DEFAULT_FORMAT, 7200, "Fri, 01 Mar 2024 09:05:07 +0200", None; "rfc 2822")]
+#[test_case(false, "%Y-%m-%d %H:%M:%S", 0, "2024-03-01 09:05:07", None; "numeric fields")]
+#[test_case(false, "%-d/%-m %_H|%-j|%e", 0, "1/3  9|61| 1", None; "padding flags")]
+#[test_case(false, "%05e|%_5d|%-5d|%10Y", 0, "00001|    1|1|0000002024", None; "widths")]
+#[test_case(false, "%N|%3N|%6N|%12N", 0, "123456789|123|123456|123456789000", None; "nanoseconds")]
+#[test_case(false, "%z|%:z|%::z|%:::z", -34200, "-0930|-09:30|-09:30:00|-09:30", None; "offsets")]
+#[test_case(false, "%:::z|%10z", 3600, "+01|+000000100", None; "short and padded offset")]
+#[test_case(false, "%s", 7200, "1709276707", None; "epoch seconds")]
+#[test_case(false, "%a %A %b %B %h %p %P", 0, "Fri Friday Mar March Mar AM am", None; "names")]
+#[test_case(false, "%^a %^B %#a %#p %^10B", 0, "FRI MARCH FRI am      MARCH", None; "case flags")]
+#[test_case(false, "%c|%x|%X|%r", 0, "Fri Mar  1 09:05:07 2024|03/01/24|09:05:07|09:05:07 AM", None; "locale formats")]
+#[test_case(false, "%D %F %R %T", 0, "03/01/24 2024-03-01 09:05 09:05:07", None; "composite formats")]
+#[test_case(false, "%C %y %g %G %V %U %W %u %w %q", 0, "20 24 24 2024 09 08 09 5 5 1", None; "calendar fields")]
+#[test_case(false, "%I %l %k", 0, "09  9  9", None; "hour padding")]
+#[test_case(false, "%Ey %Od 100%%%n%t", 0, "24 01 100%\n\t", None; "modifiers and literals")]
+#[test_case(false, "é %Y ☺", 0, "é 2024 ☺", None; "non ascii text")]
+#[test_case(false, "%Q", 0, "", Some(CustomError::InvalidFormat("%Q".to_string())); "unknown conversion")]
+#[test_case(false, "%-:d", 0, "", Some(CustomError::InvalidFormat("%-:d".to_string())); "colons on non offset")]
+#[test_case(false, "%::::z", 0, "", Some(CustomError::InvalidFormat("%::::z".to_string())); "too many colons")]
+#[test_case(false, "trailing %", 0, "", Some(CustomError::InvalidFormat("%".to_string())); "trailing percent")]
+fn test_process_date(
+    is_rfc_2822: bool,
+    format: &str,
+    offset: i32,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let date = fixed_date(offset);
+
+    match expected_error {
+        None => assert_eq!(
+            expected_result,
+            process_date(is_rfc_2822, format, chrono::Locale::POSIX, date).unwrap()
+        ),
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            process_date(is_rfc_2822, format, chrono::Locale::POSIX, date)
+        ),
+    }
+}
+
+// Expected outputs match GNU coreutils date in Asia/Kolkata
+#[test_case(None, None, None, "Fri Mar  1 09:05:07 IST 2024", None; "default format")]
+#[test_case(Some("+%Y"), None, None, "2024", None; "format operand")]
+#[test_case(Some("+"), None, None, "", None; "empty format")]
+#[test_case(None, Some("date"), None, "2024-03-01", None; "iso 8601 date")]
+#[test_case(None, Some("hours"), None, "2024-03-01T09+05:30", None; "iso 8601 hours")]
+#[test_case(None, Some("minutes"), None, "2024-03-01T09:05+05:30", None; "iso 8601 minutes")]
+#[test_case(None, Some("seconds"), None, "2024-03-01T09:05:07+05:30", None; "iso 8601 seconds")]
+#[test_case(None, Some("ns"), None, "2024-03-01T09:05:07,123456789+05:30", None; "iso 8601 nanoseconds")]
+#[test_case(None, Some("s"), None, "2024-03-01T09:05:07+05:30", None; "iso 8601 prefix")]
+#[test_case(None, None, Some("date"), "2024-03-01", None; "rfc 3339 date")]
+#[test_case(None, None, Some("seconds"), "2024-03-01 09:05:07+05:30", None; "rfc 3339 seconds")]
+#[test_case(None, None, Some("ns"), "2024-03-01 09:05:07.123456789+05:30", None; "rfc 3339 nanoseconds")]
+#[test_case(Some("%Y"), None, None, "", Some(CustomError::InvalidOperand("%Y".to_string())); "missing plus")]
+#[test_case(None, Some("weeks"), None, "", Some(CustomError::InvalidTimespec("weeks".to_string())); "unknown iso 8601 timespec")]
+#[test_case(None, None, Some("hours"), "", Some(CustomError::InvalidTimespec("hours".to_string())); "rfc 3339 without hours")]
+#[test_case(None, None, Some(""), "", Some(CustomError::InvalidTimespec("".to_string())); "empty timespec")]
+fn test_process_format(
+    format: Option<&str>,
+    iso_8601: Option<&str>,
+    rfc_3339: Option<&str>,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let zone = zone::Zone::load(Some("Asia/Kolkata")).unwrap();
+    let date = fixed_date(0).with_timezone(&zone) - chrono::Duration::minutes(330);
+    let result = process_format(format, iso_8601, rfc_3339)
+        .and_then(|format| process_date(false, format, chrono::Locale::POSIX, date));
+
+    match expected_error {
+        None => assert_eq!(Ok(expected_result.to_string()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test_case(0, "1970-01-01T00:00:00,000000000+00:00"; "utc")]
+#[test_case(-34200, "1969-12-31T14:30:00,000000000-09:30"; "negative offset")]
+fn test_process_format_iso_8601_epoch(offset: i32, expected_result: &str) {
+    let date = FixedOffset::east(offset).timestamp(0, 0);
+    let format = process_format(None, Some("ns"), None).unwrap();
+
+    assert_eq!(expected_result, process_date(false, format, chrono::Locale::POSIX, date).unwrap());
+}
+
+#[test_case(&["rdate", "-Iseconds"], &["rdate", "--iso-8601=seconds"]; "attached timespec")]
+#[test_case(&["rdate", "-uRIns"], &["rdate", "-uR", "--iso-8601=ns"]; "flags before timespec")]
+#[test_case(&["rdate", "-I", "add", "now", "1d"], &["rdate", "-I", "add", "now", "1d"]; "bare option kept")]
+#[test_case(&["rdate", "-I=hours", "-Ix"], &["rdate", "-I=hours", "--iso-8601=x"]; "equals kept")]
+#[test_case(&["rdate", "-d", "-Ix", "-v", "-Iy"], &["rdate", "-d", "-Ix", "-v", "-Iy"]; "option values kept")]
+#[test_case(&["rdate", "-d", "-d", "-Ix"], &["rdate", "-d", "-d", "--iso-8601=x"]; "date value is not an option")]
+#[test_case(&["rdate", "--", "-Ix"], &["rdate", "--", "-Ix"]; "operands kept")]
+#[test_case(&["rdate", "-yIx", "--iso-8601"], &["rdate", "-yIx", "--iso-8601"]; "other options kept")]
+fn test_process_argv(argv: &[&str], expected_result: &[&str]) {
+    let argv = argv.iter().map(OsString::from);
+    let expected_result = expected_result
+        .iter()
+        .map(OsString::from)
+        .collect::<Vec<OsString>>();
+
+    assert_eq!(expected_result, process_argv(argv));
+}
+
+// Expected outputs match GNU coreutils date -d with the clock at Wed 2024-03-06 12:30:45 UTC
+#[test_case("2 days ago", "Mon 2024-03-04 12:30:45", None; "days ago")]
+#[test_case("1 day 2 hours ago", "Thu 2024-03-07 10:30:45", None; "ago negates only the last item")]
+#[test_case("-3 weeks", "Wed 2024-02-14 12:30:45", None; "negative number")]
+#[test_case("tomorrow", "Thu 2024-03-07 12:30:45", None; "tomorrow keeps the time")]
+#[test_case("yesterday 2:30pm", "Tue 2024-03-05 14:30:00", None; "yesterday afternoon")]
+#[test_case("12am", "Wed 2024-03-06 00:00:00", None; "midnight")]
+#[test_case("next week", "Wed 2024-03-13 12:30:45", None; "next week")]
+#[test_case("last month", "Tue 2024-02-06 12:30:45", None; "last month")]
+#[test_case("friday", "Fri 2024-03-08 00:00:00", None; "weekday")]
+#[test_case("next friday 14:00", "Fri 2024-03-08 14:00:00", None; "next weekday with time")]
+#[test_case("this wednesday", "Wed 2024-03-06 00:00:00", None; "this weekday is today")]
+#[test_case("next wednesday", "Wed 2024-03-13 00:00:00", None; "next weekday skips today")]
+#[test_case("last Friday", "Fri 2024-03-01 00:00:00", None; "last weekday")]
+#[test_case("@1700000000", "Tue 2023-11-14 22:13:20", None; "epoch seconds")]
+#[test_case("@-1", "Wed 1969-12-31 23:59:59", None; "negative epoch seconds")]
+#[test_case("2024-03-01T10:00:00+02:00", "Fri 2024-03-01 08:00:00", None; "iso 8601")]
+#[test_case("2024-03-01 10:00:00+02:00", "Fri 2024-03-01 08:00:00", None; "iso 8601 with space")]
+#[test_case("2024-03-01 10:00 +0200", "Fri 2024-03-01 08:00:00", None; "separate offset")]
+#[test_case("Fri, 01 Mar 2024 10:00:00 +0200", "Fri 2024-03-01 08:00:00", None; "rfc 2822")]
+#[test_case("2024-03-01", "Fri 2024-03-01 00:00:00", None; "date only")]
+#[test_case("2024-01-31 +1 month", "Sat 2024-03-02 00:00:00", None; "month overflow")]
+#[test_case("14:00Z", "Wed 2024-03-06 14:00:00", None; "utc suffix")]
+#[test_case("banana", "", Some(CustomError::InvalidDate("banana".to_string())); "unknown word")]
+#[test_case("2 bananas", "", Some(CustomError::InvalidDate("2 bananas".to_string())); "unknown unit")]
+#[test_case("13pm", "", Some(CustomError::InvalidDate("13pm".to_string())); "invalid hour")]
+#[test_case("next", "", Some(CustomError::InvalidDate("next".to_string())); "dangling ordinal")]
+#[test_case("2024-02-30", "", Some(CustomError::InvalidDate("2024-02-30".to_string())); "invalid day")]
+#[test_case("@abc", "", Some(CustomError::InvalidDate("@abc".to_string())); "invalid epoch")]
+fn test_parse_date(value: &str, expected_result: &str, expected_error: Option<CustomError>) {
+    let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+
+    match expected_error {
+        None => assert_eq!(
+            expected_result,
+            process_date_at(false, "%a %F %T", chrono::Locale::POSIX, Some(value), &[], now).unwrap()
+        ),
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            process_date_at(false, "%a %F %T", chrono::Locale::POSIX, Some(value), &[], now)
+        ),
+    }
+}
+
+#[test]
+fn test_process_lines() {
+    let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+    let lines = std::fs::read_to_string("test_data/test_dates.txt")
+        .unwrap()
+        .lines()
+        .map(|line| Ok(line.to_string()))
+        .collect::<Vec<Result<String, CustomError>>>();
+    let mut writer = vec![];
+    let mut errors = vec![];
+
+    assert_eq!(
+        Ok(()),
+        process_lines(
+            false,
+            "%F %T",
+            chrono::Locale::POSIX,
+            lines,
+            &[],
+            now,
+            &mut writer,
+            &mut errors
+        )
+    );
+    assert_eq!(
+        "2024-03-01 10:00:00\n1970-01-01 00:00:00\n2024-03-04 12:30:45\n",
+        String::from_utf8(writer).unwrap()
+    );
+    assert_eq!(
+        "Error: line 3: invalid date provided: not a date\n",
+        String::from_utf8(errors).unwrap()
+    );
+}
+
+#[test]
+fn test_process_lines_invalid_format() {
+    let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+    let lines = vec![Ok("not a date".to_string()), Ok("@0".to_string())];
+    let mut errors = vec![];
+
+    assert_eq!(
+        Err(CustomError::InvalidFormat("%Q".to_string())),
+        process_lines(
+            false,
+            "%Q",
+            chrono::Locale::POSIX,
+            lines,
+            &[],
+            now,
+            &mut vec![],
+            &mut errors
+        )
+    );
+    assert_eq!(
+        "Error: line 1: invalid date provided: not a date\n",
+        String::from_utf8(errors).unwrap()
+    );
+}
+
+// Lines Before A Read Error Are Already Written
+#[test]
+fn test_process_lines_read_failed() {
+    let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+    let lines = vec![
+        Ok("@0".to_string()),
+        Err(CustomError::ReadFailed("-".to_string())),
+        Ok("@1".to_string()),
+    ];
+    let mut writer = vec![];
+
+    assert_eq!(
+        Err(CustomError::ReadFailed("-".to_string())),
+        process_lines(
+            false,
+            "%s",
+            chrono::Locale::POSIX,
+            lines,
+            &[],
+            now,
+            &mut writer,
+            &mut vec![]
+        )
+    );
+    assert_eq!("0\n", String::from_utf8(writer).unwrap());
+}
+
+#[test]
+fn test_process_file_handler_invalid_path() {
+    assert_eq!(
+        Err(CustomError::InvalidPath("test_data/missing.txt".to_string())),
+        process_file_handler(
+            false,
+            "%F",
+            chrono::Locale::POSIX,
+            Path::new("test_data/missing.txt"),
+            &[],
+            zone::Zone::utc(),
+            clock::Clock::System
+        )
+    );
+}
+
+// Expected outputs match GNU coreutils date with TZ set to the zone
+#[test_case("America/New_York", "2024-03-10 06:59:59Z", "2024-03-10 01:59:59 EST -05:00", None; "before spring forward")]
+#[test_case("America/New_York", "2024-03-10 07:00:00Z", "2024-03-10 03:00:00 EDT -04:00", None; "after spring forward")]
+#[test_case("America/New_York", "2024-11-03 05:59:59Z", "2024-11-03 01:59:59 EDT -04:00", None; "before fall back")]
+#[test_case("America/New_York", "2024-11-03 06:00:00Z", "2024-11-03 01:00:00 EST -05:00", None; "after fall back")]
+#[test_case("America/New_York", "1800-01-01 00:00Z", "1799-12-31 19:03:58 LMT -04:56", None; "before first transition")]
+#[test_case("America/New_York", "2100-07-01 12:00Z", "2100-07-01 08:00:00 EDT -04:00", None; "after last transition")]
+#[test_case("Europe/London", "1970-01-01 00:00Z", "1970-01-01 01:00:00 BST +01:00", None; "british standard time")]
+#[test_case("Australia/Sydney", "2024-01-15 12:00", "2024-01-15 12:00:00 AEDT +11:00", None; "southern hemisphere summer")]
+#[test_case("Asia/Kolkata", "2024-01-15 12:00", "2024-01-15 12:00:00 IST +05:30", None; "half hour offset")]
+#[test_case(":Asia/Kolkata", "2024-01-15 12:00", "2024-01-15 12:00:00 IST +05:30", None; "colon prefix")]
+#[test_case("EST5EDT,M3.2.0,M11.1.0", "2024-03-10 07:00:00Z", "2024-03-10 03:00:00 EDT -04:00", None; "posix rule")]
+#[test_case("AEST-10AEDT,M10.1.0,M4.1.0/3", "2024-01-15 12:00", "2024-01-15 12:00:00 AEDT +11:00", None; "posix southern rule")]
+#[test_case("<-03>3", "2024-01-15 12:00", "2024-01-15 12:00:00 -03 -03:00", None; "posix quoted abbreviation")]
+#[test_case("", "2024-01-15 12:00", "2024-01-15 12:00:00 UTC +00:00", None; "empty zone is utc")]
+// GNU picks the later offset for times repeated by a fall back, rdate the earlier one
+#[test_case("Europe/Paris", "2024-10-27 02:30", "2024-10-27 02:30:00 CEST +02:00", None; "ambiguous local time")]
+#[test_case("Europe/Paris", "2024-03-31 02:30", "", Some(CustomError::InvalidDate("2024-03-31 02:30".to_string())); "skipped local time")]
+#[test_case("Mars/Olympus", "2024-01-15 12:00", "", Some(CustomError::InvalidTimeZone("Mars/Olympus".to_string())); "unknown zone")]
+#[test_case("../../etc/passwd", "2024-01-15 12:00", "", Some(CustomError::InvalidTimeZone("../../etc/passwd".to_string())); "zone outside database")]
+#[test_case("EST5EDT,M13.1.0,M11.1.0", "2024-01-15 12:00", "", Some(CustomError::InvalidTimeZone("EST5EDT,M13.1.0,M11.1.0".to_string())); "invalid posix rule")]
+fn test_process_zone(
+    tz: &str,
+    date: &str,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let result = process_zone(false, Some(tz)).and_then(|zone| {
+        let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+        process_date_at(
+            false,
+            "%F %T %Z %:z",
+            chrono::Locale::POSIX,
+            Some(date),
+            &[],
+            now.with_timezone(&zone),
+        )
+    });
+
+    match expected_error {
+        None => assert_eq!(Ok(expected_result.to_string()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test]
+fn test_process_reference() {
+    let path = std::env::temp_dir().join(format!("rdate_reference_{}", std::process::id()));
+    let file = File::create(&path).unwrap();
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::new(1_700_000_000, 500);
+    let accessed = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86400);
+    file.set_times(
+        std::fs::FileTimes::new()
+            .set_modified(modified)
+            .set_accessed(accessed),
+    )
+    .unwrap();
+
+    let format = |file_time| {
+        process_reference(&path, file_time).map(|date| date.format("%F %T%.f").to_string())
+    };
+    assert_eq!(
+        Ok("2023-11-14 22:13:20.000000500".to_string()),
+        format(FileTime::Modified)
+    );
+    assert_eq!(
+        Ok("1970-01-02 00:00:00".to_string()),
+        format(FileTime::Accessed)
+    );
+    assert!(format(FileTime::Changed).is_ok());
+    assert!(matches!(
+        format(FileTime::Birth),
+        Ok(_) | Err(CustomError::TimeUnavailable(_, _))
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_process_reference_missing() {
+    assert_eq!(
+        Err(CustomError::InvalidPath("test_data/missing.txt".to_string())),
+        process_reference(Path::new("test_data/missing.txt"), FileTime::Modified)
+    );
+}
+
+#[test_case(None, FileTime::Modified; "default")]
+#[test_case(Some("mtime"), FileTime::Modified; "modified")]
+#[test_case(Some("atime"), FileTime::Accessed; "accessed")]
+#[test_case(Some("ctime"), FileTime::Changed; "changed")]
+#[test_case(Some("birth"), FileTime::Birth; "birth")]
+fn test_parse_file_time(reference_time: Option<&str>, expected_result: FileTime) {
+    assert_eq!(
+        expected_result,
+        parse_file_time(reference_time.map(String::from))
+    );
+}
+
+// Expected outputs match FreeBSD date -v, from Wednesday 2024-01-31 10:00:00 unless given
+#[test_case("UTC", "2024-01-31 10:00", &["+1m"], "Thu 2024-02-29 10:00:00", None; "month clamps to leap day")]
+#[test_case("UTC", "2024-01-31 10:00", &["-1m"], "Sun 2023-12-31 10:00:00", None; "month back across year")]
+#[test_case("UTC", "2024-01-31 10:00", &["+1m", "+1m"], "Fri 2024-03-29 10:00:00", None; "clamped day is kept")]
+#[test_case("UTC", "2024-01-31 10:00", &["+1d"], "Thu 2024-02-01 10:00:00", None; "day forward across month")]
+#[test_case("UTC", "2024-01-31 10:00", &["-31d"], "Sun 2023-12-31 10:00:00", None; "day back across year")]
+#[test_case("UTC", "2024-01-31 10:00", &["2m"], "Thu 2024-02-29 10:00:00", None; "set month clamps")]
+#[test_case("UTC", "2024-02-10 10:00", &["31d"], "", Some(CustomError::InvalidAdjustment("31d".to_string())); "set day past month end")]
+#[test_case("UTC", "2024-01-31 10:00", &["-3H"], "Wed 2024-01-31 07:00:00", None; "hours back")]
+#[test_case("UTC", "2024-01-31 10:00", &["+90M"], "Wed 2024-01-31 11:30:00", None; "minutes forward")]
+#[test_case("UTC", "2024-01-31 10:00", &["5S"], "Wed 2024-01-31 10:00:05", None; "set seconds")]
+#[test_case("UTC", "2024-01-31 10:00", &["+15H"], "Thu 2024-02-01 01:00:00", None; "hours across midnight")]
+#[test_case("UTC", "2024-01-31 10:00", &["25H"], "", Some(CustomError::InvalidAdjustment("25H".to_string())); "set hour out of range")]
+#[test_case("UTC", "2024-01-31 10:00", &["fri"], "Fri 2024-02-02 10:00:00", None; "set weekday forward")]
+#[test_case("UTC", "2024-01-31 10:00", &["mon"], "Mon 2024-01-29 10:00:00", None; "set weekday back")]
+#[test_case("UTC", "2024-01-31 10:00", &["+wed"], "Wed 2024-01-31 10:00:00", None; "next weekday includes today")]
+#[test_case("UTC", "2024-01-31 10:00", &["-wednesday"], "Wed 2024-01-31 10:00:00", None; "previous weekday includes today")]
+#[test_case("UTC", "2024-01-31 10:00", &["+mon"], "Mon 2024-02-05 10:00:00", None; "next weekday")]
+#[test_case("UTC", "2024-01-31 10:00", &["-fri"], "Fri 2024-01-26 10:00:00", None; "previous weekday")]
+#[test_case("UTC", "2024-01-31 10:00", &["+mar"], "Sun 2024-03-31 10:00:00", None; "next month name")]
+#[test_case("UTC", "2024-01-31 10:00", &["-dec"], "Sun 2023-12-31 10:00:00", None; "previous month name")]
+#[test_case("UTC", "2024-01-31 10:00", &["jun"], "Sun 2024-06-30 10:00:00", None; "set month name clamps")]
+#[test_case("UTC", "2024-01-31 10:00", &["+2w"], "Wed 2024-02-14 10:00:00", None; "weeks forward")]
+#[test_case("UTC", "2024-01-31 10:00", &["0w"], "Sun 2024-01-28 10:00:00", None; "set weekday number")]
+#[test_case("UTC", "2024-01-31 10:00", &["70y"], "Sat 1970-01-31 10:00:00", None; "two digit year in 1900s")]
+#[test_case("UTC", "2024-01-31 10:00", &["24y"], "Wed 2024-01-31 10:00:00", None; "two digit year in 2000s")]
+#[test_case("UTC", "2024-02-29 10:00", &["+1y"], "Sat 2025-03-01 10:00:00", None; "year from leap day overflows")]
+#[test_case("UTC", "2024-01-31 10:00", &["1d", "+1m", "-1d"], "Wed 2024-01-31 10:00:00", None; "last day of month")]
+#[test_case("UTC", "2024-01-31 10:00", &["+1x"], "", Some(CustomError::InvalidAdjustment("+1x".to_string())); "unknown unit")]
+#[test_case("UTC", "2024-01-31 10:00", &["+m"], "", Some(CustomError::InvalidAdjustment("+m".to_string())); "missing value")]
+#[test_case("UTC", "2024-01-31 10:00", &["+1d", "banana"], "", Some(CustomError::InvalidAdjustment("banana".to_string())); "unknown name")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", &["+1d"], "Sun 2024-03-31 12:00:00 CEST", None; "day keeps wall clock across dst")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", &["+24H"], "Sun 2024-03-31 13:00:00 CEST", None; "hours are elapsed across dst")]
+fn test_process_date_adjustments(
+    tz: &str,
+    date: &str,
+    adjustments: &[&str],
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let adjustments: Vec<String> = adjustments.iter().map(|value| value.to_string()).collect();
+    let format = match tz {
+        "UTC" => "%a %F %T",
+        _ => "%a %F %T %Z",
+    };
+    let result = process_zone(false, Some(tz)).and_then(|zone| {
+        let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+        process_date_at(
+            false,
+            format,
+            chrono::Locale::POSIX,
+            Some(date),
+            &adjustments,
+            now.with_timezone(&zone),
+        )
+    });
+
+    match expected_error {
+        None => assert_eq!(Ok(expected_result.to_string()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test_case("UTC", "2024-01-31 10:00", "1mo", "2024-02-29 10:00:00 UTC", None; "month clamps to leap day")]
+#[test_case("UTC", "2024-01-31 10:00", "P1Y1M", "2025-02-28 10:00:00 UTC", None; "iso year and month")]
+#[test_case("UTC", "2024-02-29 10:00", "1 year", "2025-02-28 10:00:00 UTC", None; "year from leap day clamps")]
+#[test_case("UTC", "2024-01-31 10:00", "-1 month", "2023-12-31 10:00:00 UTC", None; "negative month")]
+#[test_case("UTC", "2024-01-31 10:00", "90 days", "2024-04-30 10:00:00 UTC", None; "days in words")]
+#[test_case("UTC", "2024-01-31 10:00", "-1h30m", "2024-01-31 08:30:00 UTC", None; "negative compact units")]
+#[test_case("UTC", "2024-01-31 10:00", "2w, 1.5h", "2024-02-14 11:30:00 UTC", None; "weeks and fractional hours")]
+#[test_case("UTC", "2024-01-31 10:00", "PT1M29.5S", "2024-01-31 10:01:29 UTC", None; "iso fractional seconds")]
+#[test_case("UTC", "2024-01-31 10:00", "P2W", "2024-02-14 10:00:00 UTC", None; "iso weeks")]
+#[test_case("UTC", "2024-01-31 10:00", "90", "2024-01-31 10:01:30 UTC", None; "bare number is seconds")]
+#[test_case("UTC", "now", "1d", "2024-03-07 12:30:45 UTC", None; "relative to now")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", "1d", "2024-03-31 12:00:00 CEST", None; "day keeps wall clock across dst")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", "24h", "2024-03-31 13:00:00 CEST", None; "hours are elapsed across dst")]
+#[test_case("Europe/Paris", "2024-03-30 02:30", "1d", "2024-03-31 03:30:00 CEST", None; "skipped time moves past the gap")]
+#[test_case("America/New_York", "2024-11-03T05:30:00Z", "1h", "2024-11-03 01:30:00 EST", None; "hour into repeated time")]
+#[test_case("UTC", "2024-01-31 10:00", "1.5d", "", Some(CustomError::InvalidDuration("1.5d".to_string())); "fractional days")]
+#[test_case("UTC", "2024-01-31 10:00", "1h30", "", Some(CustomError::InvalidDuration("1h30".to_string())); "missing unit")]
+#[test_case("UTC", "2024-01-31 10:00", "P1H", "", Some(CustomError::InvalidDuration("P1H".to_string())); "iso hours without t")]
+#[test_case("UTC", "2024-01-31 10:00", "PT", "", Some(CustomError::InvalidDuration("PT".to_string())); "iso empty time")]
+#[test_case("UTC", "2024-01-31 10:00", "P1D1Y", "", Some(CustomError::InvalidDuration("P1D1Y".to_string())); "iso out of order")]
+#[test_case("UTC", "2024-01-31 10:00", "", "", Some(CustomError::InvalidDuration("".to_string())); "empty duration")]
+#[test_case("UTC", "someday", "1d", "", Some(CustomError::InvalidDate("someday".to_string())); "invalid date")]
+fn test_process_add(
+    tz: &str,
+    date: &str,
+    duration: &str,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let result = process_zone(false, Some(tz)).and_then(|zone| {
+        let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+        process_add(
+            false,
+            "%F %T %Z",
+            chrono::Locale::POSIX,
+            date,
+            duration,
+            now.with_timezone(&zone),
+        )
+    });
+
+    match expected_error {
+        None => assert_eq!(Ok(expected_result.to_string()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test_case("UTC", "2024-01-31 10:00", "2024-03-01 12:30:15.5", DiffOutput::Seconds, "2601015.5", None; "seconds with fraction")]
+#[test_case("UTC", "2024-03-01 12:30:15", "2024-01-31 10:00", DiffOutput::Seconds, "-2601015", None; "negative seconds")]
+#[test_case("UTC", "2024-01-31 10:00", "2024-03-01 12:30:15.5", DiffOutput::Iso8601, "P1M1DT2H30M15.5S", None; "iso 8601")]
+#[test_case("UTC", "2024-01-31 10:00", "2024-02-29 10:00", DiffOutput::Iso8601, "P1M", None; "month to shorter month")]
+#[test_case("UTC", "2023-01-15 00:00", "2024-03-15 00:00", DiffOutput::Iso8601, "P1Y2M", None; "years and months")]
+#[test_case("UTC", "2024-03-01 12:30:15", "2024-01-31 10:00", DiffOutput::Iso8601, "-P1M1DT2H30M15S", None; "negative iso 8601")]
+#[test_case("UTC", "now", "now", DiffOutput::Iso8601, "PT0S", None; "empty iso 8601")]
+#[test_case("UTC", "2024-01-31 10:00", "2025-03-01 11:01:01", DiffOutput::Human, "1 year 1 month 1 day 1 hour 1 minute 1 second", None; "human singular")]
+#[test_case("UTC", "2024-03-06", "yesterday 06:00", DiffOutput::Human, "18 hours ago", None; "human negative")]
+#[test_case("UTC", "now", "now", DiffOutput::Human, "0 seconds", None; "empty human")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", "2024-03-31 12:00", DiffOutput::Seconds, "82800", None; "seconds across dst")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", "2024-03-31 12:00", DiffOutput::Iso8601, "P1D", None; "day across dst")]
+#[test_case("Europe/Paris", "2024-03-30 12:00", "2024-03-31 11:00", DiffOutput::Iso8601, "PT22H", None; "less than a day across dst")]
+#[test_case("UTC", "2024-01-31 10:00", "someday", DiffOutput::Seconds, "", Some(CustomError::InvalidDate("someday".to_string())); "invalid date")]
+fn test_process_diff(
+    tz: &str,
+    start: &str,
+    end: &str,
+    output: DiffOutput,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let result = process_zone(false, Some(tz)).and_then(|zone| {
+        let now = chrono::Utc.ymd(2024, 3, 6).and_hms(12, 30, 45);
+        process_diff(start, end, output, now.with_timezone(&zone))
+    });
+
+    match expected_error {
+        None => assert_eq!(Ok(expected_result.to_string()), result),
+        Some(expected_error) => assert_eq!(Err(expected_error), result),
+    }
+}
+
+#[test_case("seconds", DiffOutput::Seconds; "seconds")]
+#[test_case("iso-8601", DiffOutput::Iso8601; "iso 8601")]
+#[test_case("human", DiffOutput::Human; "human")]
+fn test_parse_diff_output(output: &str, expected_result: DiffOutput) {
+    assert_eq!(expected_result, parse_diff_output(output));
+}
+
+#[test]
+fn test_cli() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+}
+
+// RFC 868 gives 2,208,988,800 for 1970 and 2,629,584,000 for May 1983
+#[test_case(0, [0x83, 0xaa, 0x7e, 0x80]; "unix epoch")]
+#[test_case(420_595_200, [0x9c, 0xbc, 0x44, 0x80]; "rfc example")]
+#[test_case(1_709_283_907, [0xe9, 0x8c, 0x18, 0xc3]; "recent")]
+#[test_case(2_085_978_496, [0x00, 0x00, 0x00, 0x00]; "first wrap in 2036")]
+#[test_case(-61_505_152, [0x80, 0x00, 0x00, 0x00]; "earliest readable")]
+#[test_case(4_233_462_143, [0x7f, 0xff, 0xff, 0xff]; "latest readable")]
+fn test_time_protocol_encode(timestamp: i64, expected_result: [u8; 4]) {
+    let date = chrono::Utc.timestamp(timestamp, 0);
+
+    assert_eq!(expected_result, time_protocol::encode(&date));
+    assert_eq!(Some(date), time_protocol::decode(expected_result));
+}
+
+#[test_case(false, 1500, "2024-03-01 09:05:07\noffset +1 seconds"; "ahead")]
+#[test_case(false, -3000, "2024-03-01 09:05:07\noffset -3 seconds"; "behind")]
+#[test_case(false, -250, "2024-03-01 09:05:07\noffset +0 seconds"; "less than a second")]
+#[test_case(false, 0, "2024-03-01 09:05:07\noffset +0 seconds"; "in sync")]
+#[test_case(true, 0, "Fri, 01 Mar 2024 09:05:07 +0000\noffset +0 seconds"; "rfc 2822")]
+fn test_process_query(is_rfc_2822: bool, offset: i64, expected_result: &str) {
+    assert_eq!(
+        Ok(expected_result.to_string()),
+        process_query(
+            is_rfc_2822,
+            "%F %T",
+            chrono::Locale::POSIX,
+            fixed_date(0),
+            chrono::Duration::microseconds(offset * 1000 + 400 * offset.signum())
+        )
+    );
+}
+
+#[test_case(time_protocol::Transport::Tcp; "tcp")]
+#[test_case(time_protocol::Transport::Udp; "udp")]
+fn test_time_protocol_localhost(transport: time_protocol::Transport) {
+    let address = match transport {
+        time_protocol::Transport::Tcp => {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            std::thread::spawn(move || time_protocol::serve_tcp(listener, clock::Clock::System));
+            address
+        }
+        time_protocol::Transport::Udp => {
+            let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let address = socket.local_addr().unwrap();
+            std::thread::spawn(move || time_protocol::serve_udp(socket, clock::Clock::System));
+            address
+        }
+    };
+
+    let before = chrono::Utc::now().timestamp();
+    let (remote, offset) = time_protocol::query(&address.to_string(), transport, clock::Clock::System).unwrap();
+    let after = chrono::Utc::now().timestamp();
+
+    // Both Ends Truncate To Whole Seconds, So The Same Clock Is Off By At Most One
+    assert!((before..=after).contains(&remote.timestamp()));
+    assert_eq!(offset, chrono::Duration::seconds(offset.num_seconds()));
+    assert!(offset.num_seconds().abs() <= 1);
+}
+
+// Fixed Clocks On Both Ends Make The Offset Exact Once The Client Drops Its Half Second
+#[test_case(time_protocol::Transport::Tcp; "tcp")]
+#[test_case(time_protocol::Transport::Udp; "udp")]
+fn test_time_protocol_fixed_clock(transport: time_protocol::Transport) {
+    let server = clock::Clock::Fixed(chrono::Utc.timestamp(1_709_283_907, 0));
+    let client = clock::Clock::Fixed(chrono::Utc.timestamp(1_709_283_900, 500_000_000));
+
+    let address = match transport {
+        time_protocol::Transport::Tcp => {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            std::thread::spawn(move || time_protocol::serve_tcp(listener, server));
+            address
+        }
+        time_protocol::Transport::Udp => {
+            let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let address = socket.local_addr().unwrap();
+            std::thread::spawn(move || time_protocol::serve_udp(socket, server));
+            address
+        }
+    };
+
+    assert_eq!(
+        Ok((server.now(), chrono::Duration::seconds(7))),
+        time_protocol::query(&address.to_string(), transport, client)
+    );
+}
+
+#[test_case(time_protocol::Transport::Tcp; "tcp")]
+#[test_case(time_protocol::Transport::Udp; "udp")]
+fn test_time_protocol_no_server(transport: time_protocol::Transport) {
+    let address = std::net::UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .unwrap()
+        .to_string();
+
+    assert_eq!(
+        Err(CustomError::QueryFailed(address.clone())),
+        time_protocol::query(&address, transport, clock::Clock::System)
+    );
+}
+
+#[test]
+fn test_time_protocol_invalid_host() {
+    assert_eq!(
+        Err(CustomError::InvalidHost("localhost:port".to_string())),
+        time_protocol::query("localhost:port", time_protocol::Transport::Tcp, clock::Clock::System)
+    );
+}
+
+#[test_case(0, 0, [0x83, 0xaa, 0x7e, 0x80, 0x00, 0x00, 0x00, 0x00]; "unix epoch")]
+#[test_case(0, 500_000_000, [0x83, 0xaa, 0x7e, 0x80, 0x80, 0x00, 0x00, 0x00]; "half a second")]
+#[test_case(1_709_283_907, 123_456_789, [0xe9, 0x8c, 0x18, 0xc3, 0x1f, 0x9a, 0xdd, 0x37]; "nanoseconds")]
+fn test_ntp_timestamp(timestamp: i64, nanoseconds: u32, expected_result: [u8; 8]) {
+    let date = chrono::Utc.timestamp(timestamp, nanoseconds);
+
+    assert_eq!(expected_result, ntp::encode_timestamp(&date));
+    assert_eq!(Some(date), ntp::decode_timestamp(&expected_result));
+}
+
+/// Answers SNTP requests on 127.0.0.1 as a server `offset` ahead of the local clock would. The
+/// first `forged` replies do not echo the request's transmit time, so clients must skip them.
+fn spawn_ntp_responder(
+    offset: chrono::Duration,
+    leap: u8,
+    stratum: u8,
+    reference: &[u8; 4],
+    forged: usize,
+) -> String {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap().to_string();
+    let reference = *reference;
+
+    std::thread::spawn(move || {
+        let mut request = [0; 48];
+        for count in 0.. {
+            let client = match socket.recv_from(&mut request) {
+                Ok((_, client)) => client,
+                Err(_) => continue,
+            };
+
+            let now = ntp::encode_timestamp(&(chrono::Utc::now() + offset));
+            let mut reply = [0; 48];
+            reply[0] = leap << 6 | 0b100_100;
+            reply[1] = stratum;
+            reply[12..16].copy_from_slice(&reference);
+            if count >= forged {
+                reply[24..32].copy_from_slice(&request[40..48]);
+            }
+            reply[32..40].copy_from_slice(&now);
+            reply[40..48].copy_from_slice(&now);
+            let _ = socket.send_to(&reply, client);
+        }
+    });
+
+    address
+}
+
+#[test_case(2500; "ahead")]
+#[test_case(-86_400_000; "a day behind")]
+fn test_ntp_query(offset: i64) {
+    let offset = chrono::Duration::milliseconds(offset);
+    let address = spawn_ntp_responder(offset, 0, 2, b"GPS\0", 0);
+
+    let before = chrono::Utc::now();
+    let sample = ntp::query(&address, std::time::Duration::from_secs(1), 0, clock::Clock::System).unwrap();
+    let after = chrono::Utc::now();
+
+    let tolerance = chrono::Duration::milliseconds(100);
+    assert_eq!(2, sample.stratum);
+    assert!((sample.offset - offset).num_milliseconds().abs() < tolerance.num_milliseconds());
+    assert!(sample.delay >= chrono::Duration::zero() && sample.delay < tolerance);
+    assert!(sample.time >= before + offset - tolerance && sample.time <= after + offset + tolerance);
+}
+
+#[test_case(1, 1, true; "retry after a forged reply")]
+#[test_case(2, 1, false; "retries used up")]
+#[test_case(1, 0, false; "no retries")]
+fn test_ntp_query_retries(forged: usize, retries: u32, is_answered: bool) {
+    let address = spawn_ntp_responder(chrono::Duration::zero(), 0, 1, b"PPS\0", forged);
+    let result = ntp::query(&address, std::time::Duration::from_millis(200), retries, clock::Clock::System);
+
+    match is_answered {
+        true => assert_eq!(1, result.unwrap().stratum),
+        false => assert_eq!(Err(CustomError::QueryFailed(address)), result),
+    }
+}
+
+#[test_case(0, 0, b"RATE", "RATE"; "kiss of death")]
+#[test_case(3, 2, b"GPS\0", "unsynchronized"; "unsynchronized server")]
+fn test_ntp_query_refused(leap: u8, stratum: u8, reference: &[u8; 4], expected_reason: &str) {
+    let address = spawn_ntp_responder(chrono::Duration::zero(), leap, stratum, reference, 0);
+
+    assert_eq!(
+        Err(CustomError::QueryRefused(
+            address.clone(),
+            expected_reason.to_string()
+        )),
+        ntp::query(&address, std::time::Duration::from_secs(1), 0, clock::Clock::System)
+    );
+}
+
+#[test]
+fn test_process_ntp() {
+    let sample = ntp::Sample {
+        time: fixed_date(0).with_timezone(&chrono::Utc),
+        offset: chrono::Duration::nanoseconds(-1_234_567),
+        delay: chrono::Duration::nanoseconds(20_500_900),
+        stratum: 2,
+    };
+
+    assert_eq!(
+        Ok("2024-03-01 10:05:07 +01:00\noffset -0.001234 seconds\ndelay 0.0205 seconds\nstratum 2"
+            .to_string()),
+        process_ntp(
+            false,
+            "%F %T %:z",
+            chrono::Locale::POSIX,
+            &sample,
+            &FixedOffset::east(3600)
+        )
+    );
+}
+
+#[test_case(0.25, Ok(std::time::Duration::from_millis(250)); "fraction")]
+#[test_case(1e-9, Ok(std::time::Duration::from_millis(1)); "at least a millisecond")]
+#[test_case(0.0, Err(CustomError::InvalidTimeout("0".to_string())); "zero")]
+#[test_case(-1.0, Err(CustomError::InvalidTimeout("-1".to_string())); "negative")]
+#[test_case(f64::INFINITY, Err(CustomError::InvalidTimeout("inf".to_string())); "infinite")]
+fn test_process_timeout(timeout: f64, expected_result: Result<std::time::Duration, CustomError>) {
+    assert_eq!(expected_result, process_timeout(timeout));
+}
+
+#[test_case(None, Ok(chrono::Locale::POSIX); "default")]
+#[test_case(Some("C"), Ok(chrono::Locale::POSIX); "c")]
+#[test_case(Some("C.UTF-8"), Ok(chrono::Locale::POSIX); "c with codeset")]
+#[test_case(Some("POSIX"), Ok(chrono::Locale::POSIX); "posix")]
+#[test_case(Some("fr_FR"), Ok(chrono::Locale::fr_FR); "name")]
+#[test_case(Some("fr_FR.UTF-8"), Ok(chrono::Locale::fr_FR); "name with codeset")]
+#[test_case(Some("be_BY.UTF-8@latin"), Ok(chrono::Locale::be_BY_latin); "codeset and modifier")]
+#[test_case(Some("fr_CA@euro"), Ok(chrono::Locale::fr_CA); "unknown modifier")]
+#[test_case(Some("fr"), Err(CustomError::InvalidLocale("fr".to_string())); "language only")]
+#[test_case(Some("xx_YY.UTF-8"), Err(CustomError::InvalidLocale("xx_YY.UTF-8".to_string())); "unknown locale")]
+fn test_process_locale(locale: Option<&str>, expected_result: Result<chrono::Locale, CustomError>) {
+    assert_eq!(expected_result, process_locale(locale));
+}
+
+#[test_case(false, chrono::Locale::fr_FR, DEFAULT_FORMAT, "ven. mars  1 09:05:07 +00:00 2024"; "french default format")]
+#[test_case(false, chrono::Locale::fr_FR, "%A %B %^a %#b %_10B", "vendredi mars VEN. MARS       mars"; "french names")]
+#[test_case(false, chrono::Locale::fr_FR, "%c|%x|%X", "ven. 01 mars 2024 09:05:07 +00:00|01/03/2024|09:05:07"; "french layouts")]
+#[test_case(false, chrono::Locale::de_DE, "%A %e. %B %Y|%x", "Freitag  1. März 2024|01.03.2024"; "german")]
+#[test_case(false, chrono::Locale::ja_JP, "%x %A %p", "2024年03月01日 金曜日 午前"; "japanese")]
+#[test_case(false, chrono::Locale::fr_FR, "%F %T %s %j", "2024-03-01 09:05:07 1709283907 061"; "numbers are unchanged")]
+#[test_case(true, chrono::Locale::fr_FR, "", "Fri, 01 Mar 2024 09:05:07 +0000"; "rfc 2822 stays english")]
+#[test_case(false, chrono::Locale::POSIX, "%c|%r", "Fri Mar  1 09:05:07 2024|09:05:07 AM"; "c locale")]
+fn test_process_date_locale(
+    is_rfc_2822: bool,
+    locale: chrono::Locale,
+    format: &str,
+    expected_result: &str,
+) {
+    assert_eq!(
+        Ok(expected_result.to_string()),
+        process_date(is_rfc_2822, format, locale, fixed_date(0))
+    );
+}
+
+#[test_case(None, None, Ok(None); "system clock")]
+#[test_case(None, Some(""), Ok(None); "empty source date epoch")]
+#[test_case(None, Some("1709283907"), Ok(Some("2024-03-01T09:05:07Z")); "source date epoch")]
+#[test_case(None, Some("-1"), Err(CustomError::InvalidSourceDateEpoch("-1".to_string())); "negative source date epoch")]
+#[test_case(None, Some("1709283907.5"), Err(CustomError::InvalidSourceDateEpoch("1709283907.5".to_string())); "fractional source date epoch")]
+#[test_case(None, Some("yesterday"), Err(CustomError::InvalidSourceDateEpoch("yesterday".to_string())); "invalid source date epoch")]
+#[test_case(Some("1709283907"), None, Ok(Some("2024-03-01T09:05:07Z")); "now in seconds")]
+#[test_case(Some("@1709283907.25"), None, Ok(Some("2024-03-01T09:05:07.250Z")); "now with fraction")]
+#[test_case(Some("-86400"), None, Ok(Some("1969-12-31T00:00:00Z")); "now before the epoch")]
+#[test_case(Some("2024-03-01 09:05:07 +0200"), None, Ok(Some("2024-03-01T07:05:07Z")); "now as a date")]
+#[test_case(Some("1709283907"), Some("0"), Ok(Some("2024-03-01T09:05:07Z")); "now wins over source date epoch")]
+#[test_case(Some("soon"), Some("0"), Err(CustomError::InvalidDate("soon".to_string())); "invalid now")]
+fn test_process_clock(
+    now: Option<&str>,
+    source_date_epoch: Option<&str>,
+    expected_result: Result<Option<&str>, CustomError>,
+) {
+    let expected_result = expected_result.map(|fixed| match fixed {
+        Some(fixed) => clock::Clock::Fixed(fixed.parse().unwrap()),
+        None => clock::Clock::System,
+    });
+
+    assert_eq!(expected_result, process_clock(now, source_date_epoch));
+}
+
+fn calendar_layout(is_monday_first: bool, is_julian: bool, is_weeks: bool) -> calendar::Layout {
+    calendar::Layout {
+        is_monday_first,
+        is_julian,
+        is_weeks,
+    }
+}
+
+#[test_case(&["9", "1752"], CalendarView::Month, calendar_layout(false, false, false), "september_1752.txt"; "gregorian changeover")]
+#[test_case(&["sep", "1752"], CalendarView::Month, calendar_layout(false, true, false), "september_1752_julian.txt"; "gregorian changeover day of year")]
+#[test_case(&["1752"], CalendarView::Month, calendar_layout(false, false, false), "1752.txt"; "changeover year")]
+#[test_case(&["February", "2024"], CalendarView::Month, calendar_layout(false, false, false), "february_2024.txt"; "leap year")]
+#[test_case(&["2", "1900"], CalendarView::Month, calendar_layout(false, false, false), "february_1900.txt"; "gregorian century")]
+#[test_case(&["2", "2000"], CalendarView::Month, calendar_layout(false, false, false), "february_2000.txt"; "gregorian leap century")]
+#[test_case(&["2", "1700"], CalendarView::Month, calendar_layout(false, false, false), "february_1700.txt"; "julian leap century")]
+#[test_case(&["1", "2021"], CalendarView::ThreeMonths, calendar_layout(true, false, true), "january_2021_three_months_weeks.txt"; "three months with iso weeks")]
+#[test_case(&["2024"], CalendarView::Year, calendar_layout(false, true, false), "2024_julian.txt"; "year with day of year")]
+#[test_case(&[], CalendarView::Month, calendar_layout(false, false, false), "february_2024.txt"; "this month")]
+fn test_process_cal(
+    operands: &[&str],
+    view: CalendarView,
+    layout: calendar::Layout,
+    expected_path: &str,
+) {
+    let operands = operands
+        .iter()
+        .map(|operand| operand.to_string())
+        .collect::<Vec<String>>();
+    let today = chrono::NaiveDate::from_ymd(2024, 2, 29);
+    let expected_result =
+        std::fs::read_to_string(Path::new("test_data/cal").join(expected_path)).unwrap();
+
+    assert_eq!(
+        Ok(expected_result.trim_end().to_string()),
+        process_cal(&operands, view, layout, chrono::Locale::POSIX, today, false)
+    );
+}
+
+#[test]
+fn test_process_cal_highlighted() {
+    let today = chrono::NaiveDate::from_ymd(2024, 2, 29);
+    let layout = calendar_layout(false, false, false);
+    let result = process_cal(
+        &[],
+        CalendarView::Month,
+        layout,
+        chrono::Locale::POSIX,
+        today,
+        true,
+    );
+
+    assert!(result.unwrap().ends_with("25 26 27 28 \x1b[7m29\x1b[0m"));
+}
+
+#[test_case(&["12", "9999"], "   November 9999         December 9999"; "last month")]
+#[test_case(&["1", "1"], "     January 1             February 1"; "first month")]
+fn test_process_cal_three_months_edges(operands: &[&str], expected_result: &str) {
+    let operands = operands
+        .iter()
+        .map(|operand| operand.to_string())
+        .collect::<Vec<String>>();
+    let today = chrono::NaiveDate::from_ymd(2024, 2, 29);
+    let layout = calendar_layout(false, false, false);
+    let result = process_cal(
+        &operands,
+        CalendarView::ThreeMonths,
+        layout,
+        chrono::Locale::POSIX,
+        today,
+        false,
+    );
+
+    // Months Outside Years 1 To 9999 Are Left Out
+    assert_eq!(Some(expected_result), result.unwrap().lines().next());
+}
+
+#[test_case(&["13", "2024"], CustomError::InvalidMonth("13".to_string()); "month out of range")]
+#[test_case(&["ju", "2024"], CustomError::InvalidMonth("ju".to_string()); "ambiguous month")]
+#[test_case(&["0"], CustomError::InvalidYear("0".to_string()); "year zero")]
+#[test_case(&["3", "10000"], CustomError::InvalidYear("10000".to_string()); "year out of range")]
+fn test_process_cal_invalid(operands: &[&str], expected_error: CustomError) {
+    let operands = operands
+        .iter()
+        .map(|operand| operand.to_string())
+        .collect::<Vec<String>>();
+    let today = chrono::NaiveDate::from_ymd(2024, 2, 29);
+    let layout = calendar_layout(false, false, false);
+
+    assert_eq!(
+        Err(expected_error),
+        process_cal(
+            &operands,
+            CalendarView::Month,
+            layout,
+            chrono::Locale::POSIX,
+            today,
+            false
+        )
+    );
+}
+
+#[test_case("3", Some(3); "number")]
+#[test_case("03", Some(3); "padded number")]
+#[test_case("mar", Some(3); "abbreviation")]
+#[test_case("SEPTEMBER", Some(9); "full name")]
+#[test_case("sept", Some(9); "prefix")]
+#[test_case("ma", None; "too short")]
+#[test_case("marchy", None; "not a name")]
+fn test_calendar_parse_month(month: &str, expected_result: Option<u32>) {
+    assert_eq!(expected_result, calendar::parse_month(month).ok());
+}
+
+// Julian dates before the reform, as cal(1) shows them
+#[test_case(1752, 9, 2, Some((1752, 9, 13)); "last julian day")]
+#[test_case(1752, 9, 3, None; "skipped day")]
+#[test_case(1752, 9, 13, None; "last skipped day")]
+#[test_case(1752, 9, 14, Some((1752, 9, 14)); "first gregorian day")]
+#[test_case(1700, 2, 29, Some((1700, 3, 11)); "julian leap day")]
+#[test_case(1900, 2, 29, None; "gregorian non leap day")]
+#[test_case(1, 1, 1, Some((0, 12, 30)); "first day of the era")]
+fn test_calendar_civil_date(
+    year: i32,
+    month: u32,
+    day: u32,
+    expected_result: Option<(i32, u32, u32)>,
+) {
+    let expected_result =
+        expected_result.map(|(year, month, day)| chrono::NaiveDate::from_ymd(year, month, day));
+
+    assert_eq!(expected_result, calendar::civil_date(year, month, day));
+}
+
+#[test_case(0, false, "0:00:00"; "zero")]
+#[test_case(59_999, false, "0:00:59"; "whole seconds")]
+#[test_case(3_723_456, true, "1:02:03.4"; "tenths")]
+#[test_case(90_061_000, false, "25:01:01"; "past a day")]
+#[test_case(-61_500, true, "-0:01:01.5"; "negative")]
+fn test_format_clock(milliseconds: i64, is_tenths: bool, expected_result: &str) {
+    let duration = chrono::Duration::milliseconds(milliseconds);
+
+    assert_eq!(expected_result, duration::format_clock(duration, is_tenths));
+}
+
+// 1 day is 23 hours across the spring forward in Paris
+#[test_case("5m", "Europe/Paris", 300, None; "minutes")]
+#[test_case("1h30.5m", "UTC", 5430, None; "fraction")]
+#[test_case("PT90S", "UTC", 90, None; "iso 8601")]
+#[test_case("1 day", "Europe/Paris", 82_800, None; "day across dst")]
+#[test_case("0s", "UTC", 0, None; "zero")]
+#[test_case("-5m", "UTC", 0, Some(CustomError::InvalidDuration("-5m".to_string())); "negative")]
+#[test_case("soon", "UTC", 0, Some(CustomError::InvalidDuration("soon".to_string())); "invalid")]
+fn test_process_countdown(
+    duration: &str,
+    tz: &str,
+    expected_result: i64,
+    expected_error: Option<CustomError>,
+) {
+    let zone = zone::Zone::load(Some(tz)).unwrap();
+    let now = chrono::Utc.ymd(2024, 3, 30).and_hms(12, 0, 0).with_timezone(&zone);
+
+    match expected_error {
+        None => assert_eq!(
+            Ok(chrono::Duration::seconds(expected_result)),
+            process_countdown(duration, now)
+        ),
+        Some(error) => assert_eq!(Err(error), process_countdown(duration, now)),
+    }
+}
+
+#[test_case("17:00", 28_492_876_543_211, None; "later today")]
+#[test_case("09:05:07.5", 376_543_211, None; "fraction of a second")]
+#[test_case("tomorrow 09:00", 86_092_876_543_211, None; "tomorrow")]
+#[test_case("09:00", 0, None; "already passed")]
+#[test_case("teatime", 0, Some(CustomError::InvalidDate("teatime".to_string())); "invalid")]
+fn test_process_until(date: &str, expected_result: i64, expected_error: Option<CustomError>) {
+    match expected_error {
+        None => assert_eq!(
+            Ok(chrono::Duration::nanoseconds(expected_result)),
+            process_until(date, fixed_date(0))
+        ),
+        Some(error) => assert_eq!(Err(error), process_until(date, fixed_date(0))),
+    }
+}
+
+/// Time that only moves when a timer sleeps, or by `step` each time it is read
+struct FakeTicker {
+    now: chrono::Duration,
+    step: chrono::Duration,
+    sleeps: Vec<chrono::Duration>,
+}
+
+impl FakeTicker {
+    fn new(step: chrono::Duration) -> FakeTicker {
+        FakeTicker {
+            now: chrono::Duration::zero(),
+            step,
+            sleeps: vec![],
+        }
+    }
+}
+
+impl timer::Ticker for FakeTicker {
+    fn elapsed(&mut self) -> chrono::Duration {
+        let now = self.now;
+        self.now = self.now + self.step;
+        now
+    }
+
+    fn sleep(&mut self, duration: chrono::Duration) {
+        self.sleeps.push(duration);
+        self.now = self.now + duration;
+    }
+}
+
+#[test_case(2500, false, "0:00:03\n0:00:02\n0:00:01\n0:00:00\n", &[500, 1000, 1000]; "lines")]
+#[test_case(2000, true, "\r\x1b[K0:00:02\r\x1b[K0:00:01\r\x1b[K0:00:00\n", &[1000, 1000]; "live")]
+#[test_case(0, false, "0:00:00\n", &[]; "zero")]
+fn test_timer_countdown(
+    milliseconds: i64,
+    is_live: bool,
+    expected_result: &str,
+    expected_sleeps: &[i64],
+) {
+    let mut ticker = FakeTicker::new(chrono::Duration::zero());
+    let mut display = timer::Display {
+        output: vec![],
+        is_live,
+        is_echoed: false,
+    };
+    let total = chrono::Duration::milliseconds(milliseconds);
+
+    timer::countdown(total, &mut display, &mut ticker).unwrap();
+
+    let expected_sleeps = expected_sleeps
+        .iter()
+        .map(|&sleep| chrono::Duration::milliseconds(sleep))
+        .collect::<Vec<chrono::Duration>>();
+    assert_eq!(expected_result, String::from_utf8(display.output).unwrap());
+    assert_eq!(expected_sleeps, ticker.sleeps);
+}
+
+// Each read of the fake time moves it on by 0.7 seconds
+#[test_case(false, false, "0:00:00\nlap 1  0:00:00.7  total 0:00:00.7\n0:00:01\nlap 2  0:00:01.4  total 0:00:02.1\n0:00:02\ntotal 0:00:03.5\n"; "lines")]
+#[test_case(true, true, "\r\x1b[K0:00:00.0\x1b[1A\r\x1b[Klap 1  0:00:00.7  total 0:00:00.7\n\r\x1b[K0:00:01.4\x1b[1A\r\x1b[Klap 2  0:00:01.4  total 0:00:02.1\n\r\x1b[K0:00:02.8\r\x1b[Ktotal 0:00:03.5\n"; "live")]
+fn test_timer_stopwatch(is_live: bool, is_echoed: bool, expected_result: &str) {
+    let (sender, laps) = std::sync::mpsc::channel();
+    sender.send(()).unwrap();
+    sender.send(()).unwrap();
+    drop(sender);
+
+    let mut ticker = FakeTicker::new(chrono::Duration::milliseconds(700));
+    let mut display = timer::Display {
+        output: vec![],
+        is_live,
+        is_echoed,
+    };
+
+    timer::stopwatch(&laps, &mut display, &mut ticker).unwrap();
+
+    assert_eq!(expected_result, String::from_utf8(display.output).unwrap());
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Duration, SubsecRound, TimeZone, Utc};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::thread;
+
+use crate::clock::Clock;
+use crate::CustomError;
+
+/// The well known port of the Time Protocol
+pub const PORT: u16 = 37;
+
+/// Seconds from 1900-01-01, where RFC 868 and NTP count from, to the Unix epoch
+pub const EPOCH_OFFSET: i64 = 2_208_988_800;
+
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// The 32 bit big endian count of seconds since 1900 that the server sends
+pub fn encode(date: &DateTime<Utc>) -> [u8; 4] {
+    let seconds = (date.timestamp() + EPOCH_OFFSET).rem_euclid(1 << 32);
+
+    (seconds as u32).to_be_bytes()
+}
+
+/// Counts below 2^31 are taken to have wrapped in 2036, so this reads times from 1968 to 2104
+pub fn decode(bytes: [u8; 4]) -> Option<DateTime<Utc>> {
+    let seconds = u32::from_be_bytes(bytes) as i64;
+    let seconds = match seconds < 1 << 31 {
+        true => seconds + (1 << 32),
+        false => seconds,
+    };
+
+    Utc.timestamp_opt(seconds - EPOCH_OFFSET, 0).single()
+}
+
+/// Asks `host` for its time, returning it with how far it is ahead of the local clock, measured
+/// from the middle of the round trip. The protocol only sends whole seconds, so the local time is
+/// truncated as well and the offset is only good to a second either way.
+pub fn query(
+    host: &str,
+    transport: Transport,
+    clock: Clock,
+) -> Result<(DateTime<Utc>, Duration), CustomError> {
+    let addresses = resolve(host, PORT)?;
+    let failed = || CustomError::QueryFailed(host.to_string());
+
+    let sent = clock.now();
+    let bytes = addresses
+        .iter()
+        .find_map(|address| match transport {
+            Transport::Tcp => query_tcp(address),
+            Transport::Udp => query_udp(address),
+        })
+        .ok_or_else(failed)?;
+    let received = clock.now();
+
+    let remote = decode(bytes).ok_or_else(failed)?;
+    let local = (sent + received.signed_duration_since(sent) / 2).trunc_subsecs(0);
+
+    Ok((remote, remote.signed_duration_since(local)))
+}
+
+/// `HOST`, `HOST:PORT`, `[IPV6]` or `[IPV6]:PORT`, where the port defaults to `port`
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, CustomError> {
+    let addresses = host.to_socket_addrs().or_else(|_| {
+        let name = host.trim_start_matches('[').trim_end_matches(']');
+        (name, port).to_socket_addrs()
+    });
+
+    match addresses.map(|addresses| addresses.collect::<Vec<SocketAddr>>()) {
+        Ok(addresses) if !addresses.is_empty() => Ok(addresses),
+        _ => Err(CustomError::InvalidHost(host.to_string())),
+    }
+}
+
+/// The server sends the time as soon as the connection opens, then closes it
+fn query_tcp(address: &SocketAddr) -> Option<[u8; 4]> {
+    let mut stream = TcpStream::connect_timeout(address, TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+
+    let mut bytes = [0; 4];
+    stream.read_exact(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Any datagram, even an empty one, asks for the time
+fn query_udp(address: &SocketAddr) -> Option<[u8; 4]> {
+    let socket = connect_udp(address).ok()?;
+    socket.set_read_timeout(Some(TIMEOUT)).ok()?;
+    socket.send(&[]).ok()?;
+
+    let mut bytes = [0; 4];
+    match socket.recv(&mut bytes).ok()? {
+        4 => Some(bytes),
+        _ => None,
+    }
+}
+
+/// A UDP socket on an ephemeral port of the same family as `address`, that only hears from it
+pub fn connect_udp(address: &SocketAddr) -> std::io::Result<UdpSocket> {
+    let local: SocketAddr = match address {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(address)?;
+
+    Ok(socket)
+}
+
+/// Serves the local time on `port` over both TCP and UDP until the process is stopped
+pub fn serve(port: u16, clock: Clock) -> Result<(), CustomError> {
+    let address: SocketAddr = ([0, 0, 0, 0], port).into();
+    let bind_failed = || CustomError::BindFailed(address.to_string());
+
+    let listener = TcpListener::bind(address).map_err(|_| bind_failed())?;
+    let socket = UdpSocket::bind(address).map_err(|_| bind_failed())?;
+
+    thread::spawn(move || serve_udp(socket, clock));
+    serve_tcp(listener, clock);
+    Ok(())
+}
+
+/// A client that goes away before reading the time does not stop the server
+pub fn serve_tcp(listener: TcpListener, clock: Clock) {
+    for mut stream in listener.incoming().flatten() {
+        let _ = stream.write_all(&encode(&clock.now()));
+    }
+}
+
+pub fn serve_udp(socket: UdpSocket, clock: Clock) {
+    let mut buffer = [0; 512];
+
+    loop {
+        if let Ok((_, client)) = socket.recv_from(&mut buffer) {
+            let _ = socket.send_to(&encode(&clock.now()), client);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::Duration;
+use std::io::{self, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Instant;
+
+use crate::duration;
+
+/// How often a live stopwatch redraws, fast enough for its tenths of a second
+const LIVE_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+const LINE_TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Returns to the start of the line and clears it, so the next time replaces the last one
+const REDRAW: &str = "\r\x1b[K";
+
+/// Moves up over the line a terminal echoes when Enter is pressed
+const CURSOR_UP: &str = "\x1b[1A";
+
+/// Measures the time since a timer started, so tests can run one without waiting for it
+pub trait Ticker {
+    fn elapsed(&mut self) -> Duration;
+
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// Real time from a monotonic clock, which changes to the wall clock do not move
+pub struct SystemTicker {
+    start: Instant,
+}
+
+impl SystemTicker {
+    pub fn start() -> SystemTicker {
+        SystemTicker {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Ticker for SystemTicker {
+    fn elapsed(&mut self) -> Duration {
+        Duration::from_std(self.start.elapsed()).unwrap_or_else(|_| Duration::max_value())
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        std::thread::sleep(duration.to_std().unwrap_or_default());
+    }
+}
+
+/// Where the times go: redrawn in place on a terminal, or one per line otherwise
+pub struct Display<W: Write> {
+    pub output: W,
+    pub is_live: bool,
+    pub is_echoed: bool,
+}
+
+impl<W: Write> Display<W> {
+    fn show(&mut self, text: &str) -> io::Result<()> {
+        match self.is_live {
+            true => write!(self.output, "{}{}", REDRAW, text)?,
+            false => writeln!(self.output, "{}", text)?,
+        }
+        self.output.flush()
+    }
+
+    /// A line that stays, such as a lap, above the live time
+    fn keep(&mut self, text: &str) -> io::Result<()> {
+        match (self.is_live, self.is_echoed) {
+            (true, true) => write!(self.output, "{}{}", CURSOR_UP, REDRAW)?,
+            (true, false) => write!(self.output, "{}", REDRAW)?,
+            _ => (),
+        }
+        writeln!(self.output, "{}", text)?;
+        self.output.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.is_live {
+            true => writeln!(self.output),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Counts down from `total`, showing the whole seconds left rounded up, and returns at zero
+pub fn countdown<W: Write>(
+    total: Duration,
+    display: &mut Display<W>,
+    ticker: &mut impl Ticker,
+) -> io::Result<()> {
+    let mut shown = None;
+
+    loop {
+        let remaining = (total - ticker.elapsed()).max(Duration::zero());
+        let seconds = ceil_seconds(remaining);
+        if shown != Some(seconds) {
+            display.show(&duration::format_clock(Duration::seconds(seconds), false))?;
+            shown = Some(seconds);
+        }
+        if seconds == 0 {
+            return display.finish();
+        }
+
+        // Wake When The Display Next Changes
+        ticker.sleep(remaining - Duration::seconds(seconds - 1));
+    }
+}
+
+/// Shows the elapsed time until `laps` closes, with a lap for each message on it. A live display
+/// redraws in tenths of a second, while lines come once a second.
+pub fn stopwatch<W: Write>(
+    laps: &Receiver<()>,
+    display: &mut Display<W>,
+    ticker: &mut impl Ticker,
+) -> io::Result<()> {
+    let tick = match display.is_live {
+        true => LIVE_TICK,
+        false => LINE_TICK,
+    };
+    let mut shown = None;
+    let mut count = 0;
+    let mut lap_start = Duration::zero();
+
+    loop {
+        let elapsed = ticker.elapsed();
+        let text = duration::format_clock(elapsed, display.is_live);
+        if shown.as_ref() != Some(&text) {
+            display.show(&text)?;
+            shown = Some(text);
+        }
+
+        match laps.recv_timeout(tick) {
+            Ok(()) => {
+                let elapsed = ticker.elapsed();
+                count += 1;
+                display.keep(&format!(
+                    "lap {}  {}  total {}",
+                    count,
+                    duration::format_clock(elapsed - lap_start, true),
+                    duration::format_clock(elapsed, true)
+                ))?;
+                lap_start = elapsed;
+
+                // The Lap Took The Live Time's Line, So It Is Drawn Again Below
+                if display.is_live {
+                    shown = None;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                // The End Of Input Is Not Echoed, So The Total Replaces The Live Time
+                let total = duration::format_clock(ticker.elapsed(), true);
+                display.show(&format!("total {}", total))?;
+                return display.finish();
+            }
+        }
+    }
+}
+
+fn ceil_seconds(duration: Duration) -> i64 {
+    let seconds = duration.num_seconds();
+
+    match duration > Duration::seconds(seconds) {
+        true => seconds + 1,
+        false => seconds,
+    }
+}
@@ -0,0 +1,551 @@
+use chrono::{Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::CustomError;
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+const LOCALTIME: &str = "/etc/localtime";
+const TZIF_HEADER_SIZE: usize = 44;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LocalType {
+    offset: i32,
+    is_dst: bool,
+    abbreviation: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDate {
+    /// `Jn`, 1 to 365, never counting February 29
+    Julian(u32),
+    /// `n`, 0 to 365, counting February 29
+    Ordinal(u32),
+    /// `Mm.w.d`, day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`
+    MonthWeekDay(u32, u32, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    date: RuleDate,
+    /// Seconds after local midnight, which may be negative or past 24 hours
+    time: i64,
+}
+
+/// A POSIX TZ string, as found in TZ itself or in the footer of a TZif file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Fixed(LocalType),
+    Alternating {
+        standard: LocalType,
+        dst: LocalType,
+        start: Transition,
+        end: Transition,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ZoneInfo {
+    /// The UTC time of each transition and the index of the local type it switches to
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalType>,
+    /// Used after the last transition, or for every time when there are none
+    rule: Option<Rule>,
+}
+
+/// An IANA time zone read from the TZif database, or a POSIX TZ string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    info: Arc<ZoneInfo>,
+}
+
+/// The offset of a `Zone` at some instant, displayed as its abbreviation
+#[derive(Clone, PartialEq, Eq)]
+pub struct ZoneOffset {
+    info: Arc<ZoneInfo>,
+    local_type: LocalType,
+}
+
+impl Zone {
+    /// Loads a zone the way TZ is read: a name under TZDIR or /usr/share/zoneinfo, an absolute
+    /// TZif path or a POSIX TZ string, falling back to /etc/localtime when `tz` is not set
+    pub fn load(tz: Option<&str>) -> Result<Zone, CustomError> {
+        let tz = match tz {
+            Some(tz) => tz.strip_prefix(':').unwrap_or(tz),
+            None => return Ok(Zone::from_file(Path::new(LOCALTIME)).unwrap_or_else(Zone::utc)),
+        };
+        if tz.is_empty() {
+            return Ok(Zone::utc());
+        }
+
+        let invalid = || CustomError::InvalidTimeZone(tz.to_string());
+        let path = match tz.starts_with('/') {
+            true => PathBuf::from(tz),
+            false if tz.split('/').any(|component| component == "..") => return Err(invalid()),
+            false => std::env::var_os("TZDIR")
+                .map_or_else(|| PathBuf::from(ZONEINFO), PathBuf::from)
+                .join(tz),
+        };
+
+        if let Some(zone) = Zone::from_file(&path) {
+            return Ok(zone);
+        }
+
+        let rule = parse_rule(tz).ok_or_else(invalid)?;
+        Ok(Zone::new(vec![], vec![], Some(rule)))
+    }
+
+    pub fn utc() -> Zone {
+        let utc = LocalType {
+            offset: 0,
+            is_dst: false,
+            abbreviation: "UTC".to_string(),
+        };
+        Zone::new(vec![], vec![], Some(Rule::Fixed(utc)))
+    }
+
+    fn new(transitions: Vec<(i64, usize)>, types: Vec<LocalType>, rule: Option<Rule>) -> Zone {
+        Zone {
+            info: Arc::new(ZoneInfo {
+                transitions,
+                types,
+                rule,
+            }),
+        }
+    }
+
+    fn from_file(path: &Path) -> Option<Zone> {
+        let bytes = std::fs::read(path).ok()?;
+        parse_tzif(&bytes)
+    }
+
+    fn offset(&self, local_type: LocalType) -> ZoneOffset {
+        ZoneOffset {
+            info: self.info.clone(),
+            local_type,
+        }
+    }
+}
+
+impl ZoneInfo {
+    fn local_type_at(&self, timestamp: i64) -> LocalType {
+        let is_after_transitions = self
+            .transitions
+            .last()
+            .is_none_or(|&(last, _)| timestamp >= last);
+
+        if let (true, Some(rule)) = (is_after_transitions, &self.rule) {
+            return rule.local_type_at(timestamp);
+        }
+
+        // Times Before The First Transition Use The First Local Type
+        let index = self
+            .transitions
+            .partition_point(|&(time, _)| time <= timestamp);
+        match index {
+            0 => self.types[0].clone(),
+            _ => self.types[self.transitions[index - 1].1].clone(),
+        }
+    }
+}
+
+impl Rule {
+    fn local_type_at(&self, timestamp: i64) -> LocalType {
+        let (standard, dst, start, end) = match self {
+            Rule::Fixed(local_type) => return local_type.clone(),
+            Rule::Alternating {
+                standard,
+                dst,
+                start,
+                end,
+            } => (standard, dst, start, end),
+        };
+
+        let year = NaiveDateTime::from_timestamp_opt(timestamp + standard.offset as i64, 0)
+            .map_or(1970, |date| date.year());
+
+        // Each Transition Is Written In The Local Time In Effect Before It
+        let start = start.local_timestamp(year) - standard.offset as i64;
+        let end = end.local_timestamp(year) - dst.offset as i64;
+
+        let is_dst = match start < end {
+            true => start <= timestamp && timestamp < end,
+            false => !(end <= timestamp && timestamp < start),
+        };
+        match is_dst {
+            true => dst.clone(),
+            false => standard.clone(),
+        }
+    }
+}
+
+impl Transition {
+    fn local_timestamp(&self, year: i32) -> i64 {
+        self.date
+            .date(year)
+            .map_or(0, |date| date.and_hms(0, 0, 0).timestamp())
+            + self.time
+    }
+}
+
+impl RuleDate {
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        let is_leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+
+        match *self {
+            RuleDate::Julian(day) if is_leap && day >= 60 => NaiveDate::from_yo_opt(year, day + 1),
+            RuleDate::Julian(day) => NaiveDate::from_yo_opt(year, day),
+            RuleDate::Ordinal(day) => NaiveDate::from_yo_opt(year, day + 1)
+                .or_else(|| NaiveDate::from_ymd_opt(year, 12, 31)),
+            RuleDate::MonthWeekDay(month, week, weekday) => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let first_weekday = (weekday + 7 - first.weekday().num_days_from_sunday()) % 7;
+
+                // Week 5 Means The Last One, Which May Be The Fourth
+                (0..=week - 1).rev().find_map(|week| {
+                    NaiveDate::from_ymd_opt(year, month, 1 + first_weekday + week * 7)
+                })
+            }
+        }
+    }
+}
+
+impl Offset for ZoneOffset {
+    fn fix(&self) -> FixedOffset {
+        FixedOffset::east(self.local_type.offset)
+    }
+}
+
+impl fmt::Display for ZoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.local_type.abbreviation)
+    }
+}
+
+impl fmt::Debug for ZoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.local_type.abbreviation, self.fix())
+    }
+}
+
+impl TimeZone for Zone {
+    type Offset = ZoneOffset;
+
+    fn from_offset(offset: &ZoneOffset) -> Zone {
+        Zone {
+            info: offset.info.clone(),
+        }
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<ZoneOffset> {
+        self.offset_from_local_datetime(&local.and_hms(0, 0, 0))
+    }
+
+    /// A local time inside a DST gap has no offset and one inside an overlap has two
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<ZoneOffset> {
+        let timestamp = local.timestamp();
+
+        let mut candidates: Vec<(i64, LocalType)> = [-86400, 86400]
+            .iter()
+            .map(|day| self.info.local_type_at(timestamp + day))
+            .map(|local_type| (timestamp - local_type.offset as i64, local_type))
+            .filter(|(utc, local_type)| self.info.local_type_at(*utc) == *local_type)
+            .collect();
+        candidates.sort_by_key(|&(utc, _)| utc);
+        candidates.dedup_by_key(|&mut (utc, _)| utc);
+
+        match candidates.as_slice() {
+            [] => LocalResult::None,
+            [(_, local_type)] => LocalResult::Single(self.offset(local_type.clone())),
+            [(_, earliest), .., (_, latest)] => {
+                LocalResult::Ambiguous(self.offset(earliest.clone()), self.offset(latest.clone()))
+            }
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> ZoneOffset {
+        self.offset_from_utc_datetime(&utc.and_hms(0, 0, 0))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> ZoneOffset {
+        self.offset(self.info.local_type_at(utc.timestamp()))
+    }
+}
+
+/// The counts from a TZif header, in file order
+struct Header {
+    version: u8,
+    is_ut_count: usize,
+    is_std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Option<Header> {
+        if bytes.get(..4)? != b"TZif" {
+            return None;
+        }
+
+        let count = |index: usize| -> Option<usize> {
+            let start = 20 + index * 4;
+            let bytes: [u8; 4] = bytes.get(start..start + 4)?.try_into().ok()?;
+            Some(u32::from_be_bytes(bytes) as usize)
+        };
+
+        Some(Header {
+            version: *bytes.get(4)?,
+            is_ut_count: count(0)?,
+            is_std_count: count(1)?,
+            leap_count: count(2)?,
+            time_count: count(3)?,
+            type_count: count(4)?,
+            char_count: count(5)?,
+        })
+    }
+
+    fn data_size(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.is_std_count
+            + self.is_ut_count
+    }
+}
+
+/// Reads a TZif file (RFC 8536), preferring the 64-bit data and POSIX footer of version 2+
+fn parse_tzif(bytes: &[u8]) -> Option<Zone> {
+    let header = Header::parse(bytes)?;
+    if header.version == 0 {
+        let (info, _) = parse_tzif_data(bytes, TZIF_HEADER_SIZE, &header, 4)?;
+        return Some(Zone {
+            info: Arc::new(info),
+        });
+    }
+
+    let start = TZIF_HEADER_SIZE + header.data_size(4);
+    let header = Header::parse(bytes.get(start..)?)?;
+    let (mut info, end) = parse_tzif_data(bytes, start + TZIF_HEADER_SIZE, &header, 8)?;
+
+    let footer = bytes.get(end..)?.strip_prefix(b"\n")?;
+    let footer = &footer[..footer.iter().position(|&byte| byte == b'\n')?];
+    info.rule = std::str::from_utf8(footer).ok().and_then(parse_rule);
+
+    Some(Zone {
+        info: Arc::new(info),
+    })
+}
+
+fn parse_tzif_data(
+    bytes: &[u8],
+    start: usize,
+    header: &Header,
+    time_size: usize,
+) -> Option<(ZoneInfo, usize)> {
+    let end = start + header.data_size(time_size);
+    let data = bytes.get(start..end)?;
+
+    let (times, rest) = data.split_at(header.time_count * time_size);
+    let (indices, rest) = rest.split_at(header.time_count);
+    let (types, rest) = rest.split_at(header.type_count * 6);
+    let abbreviations = &rest[..header.char_count];
+
+    let transitions: Vec<(i64, usize)> = times
+        .chunks(time_size)
+        .zip(indices)
+        .map(|(time, &index)| {
+            let time = match time_size {
+                4 => i32::from_be_bytes(time.try_into().unwrap()) as i64,
+                _ => i64::from_be_bytes(time.try_into().unwrap()),
+            };
+            (time, index as usize)
+        })
+        .collect();
+
+    let types: Vec<LocalType> = types
+        .chunks(6)
+        .map(|local_type| {
+            let abbreviation = abbreviations
+                .get(local_type[5] as usize..)
+                .unwrap_or_default();
+            let length = abbreviation
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(abbreviation.len());
+
+            LocalType {
+                offset: i32::from_be_bytes(local_type[..4].try_into().unwrap()),
+                is_dst: local_type[4] != 0,
+                abbreviation: String::from_utf8_lossy(&abbreviation[..length]).to_string(),
+            }
+        })
+        .collect();
+
+    let is_valid = !types.is_empty() && transitions.iter().all(|&(_, index)| index < types.len());
+    match is_valid {
+        true => Some((
+            ZoneInfo {
+                transitions,
+                types,
+                rule: None,
+            },
+            end,
+        )),
+        false => None,
+    }
+}
+
+/// Parses a POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3` or `<+0530>-5:30`
+fn parse_rule(value: &str) -> Option<Rule> {
+    let (abbreviation, rest) = parse_abbreviation(value)?;
+    let (offset, rest) = parse_duration(rest)?;
+
+    // POSIX Offsets Are West Of UTC, So EST5 Is UTC-5
+    let standard = LocalType {
+        offset: -offset as i32,
+        is_dst: false,
+        abbreviation,
+    };
+    if rest.is_empty() {
+        return Some(Rule::Fixed(standard));
+    }
+
+    let (abbreviation, rest) = parse_abbreviation(rest)?;
+    let (offset, rest) = match rest.is_empty() || rest.starts_with(',') {
+        true => (standard.offset + 3600, rest),
+        false => parse_duration(rest).map(|(offset, rest)| (-offset as i32, rest))?,
+    };
+    let dst = LocalType {
+        offset,
+        is_dst: true,
+        abbreviation,
+    };
+
+    // Without Rules The US Ones Apply, As In glibc
+    let (start, end) = match rest.strip_prefix(',') {
+        Some(rest) => {
+            let (start, rest) = parse_transition(rest)?;
+            let (end, rest) = parse_transition(rest.strip_prefix(',')?)?;
+            if !rest.is_empty() {
+                return None;
+            }
+            (start, end)
+        }
+        None => (
+            Transition {
+                date: RuleDate::MonthWeekDay(3, 2, 0),
+                time: 7200,
+            },
+            Transition {
+                date: RuleDate::MonthWeekDay(11, 1, 0),
+                time: 7200,
+            },
+        ),
+    };
+
+    Some(Rule::Alternating {
+        standard,
+        dst,
+        start,
+        end,
+    })
+}
+
+/// At least three letters, or any `<...>` quoted name such as `<-03>`
+fn parse_abbreviation(value: &str) -> Option<(String, &str)> {
+    let (abbreviation, rest) = match value.strip_prefix('<') {
+        Some(quoted) => {
+            let end = quoted.find('>')?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => {
+            let end = value
+                .find(|character: char| !character.is_ascii_alphabetic())
+                .unwrap_or(value.len());
+            value.split_at(end)
+        }
+    };
+
+    match abbreviation.len() >= 3 {
+        true => Some((abbreviation.to_string(), rest)),
+        false => None,
+    }
+}
+
+/// `[+-]hh[:mm[:ss]]` in seconds
+fn parse_duration(value: &str) -> Option<(i64, &str)> {
+    let (sign, value) = match value.as_bytes().first()? {
+        b'-' => (-1, &value[1..]),
+        b'+' => (1, &value[1..]),
+        _ => (1, value),
+    };
+
+    let (hours, mut rest) = parse_number(value)?;
+    let mut seconds = hours * 3600;
+    for unit in [60, 1] {
+        match rest.strip_prefix(':').and_then(parse_number) {
+            Some((value, remaining)) => {
+                seconds += value * unit;
+                rest = remaining;
+            }
+            None => break,
+        }
+    }
+
+    match hours <= 167 {
+        true => Some((sign * seconds, rest)),
+        false => None,
+    }
+}
+
+fn parse_number(value: &str) -> Option<(i64, &str)> {
+    let end = value
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(value.len());
+    Some((value[..end].parse().ok()?, &value[end..]))
+}
+
+/// `Jn`, `n` or `Mm.w.d`, with an optional `/time` defaulting to 02:00:00
+fn parse_transition(value: &str) -> Option<(Transition, &str)> {
+    let (date, rest) = match value.as_bytes().first()? {
+        b'J' => {
+            let (day, rest) = parse_number(&value[1..])?;
+            (
+                (1..=365)
+                    .contains(&day)
+                    .then_some(RuleDate::Julian(day as u32))?,
+                rest,
+            )
+        }
+        b'M' => {
+            let (month, rest) = parse_number(&value[1..])?;
+            let (week, rest) = parse_number(rest.strip_prefix('.')?)?;
+            let (weekday, rest) = parse_number(rest.strip_prefix('.')?)?;
+
+            let is_valid =
+                (1..=12).contains(&month) && (1..=5).contains(&week) && (0..=6).contains(&weekday);
+            let date = RuleDate::MonthWeekDay(month as u32, week as u32, weekday as u32);
+            (is_valid.then_some(date)?, rest)
+        }
+        _ => {
+            let (day, rest) = parse_number(value)?;
+            (
+                (0..=365)
+                    .contains(&day)
+                    .then_some(RuleDate::Ordinal(day as u32))?,
+                rest,
+            )
+        }
+    };
+
+    let (time, rest) = match rest.strip_prefix('/') {
+        Some(time) => parse_duration(time)?,
+        None => (7200, rest),
+    };
+
+    Some((Transition { date, time }, rest))
+}
@@ -0,0 +1,359 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::{BufRead, Write};
+use std::process::Stdio;
+use test_case::test_case;
+
+/// 2024-03-01 09:05:07 UTC, a Friday in a leap year
+const NOW: &str = "1709283907";
+
+/// rdate in UTC and the C locale whatever the environment running the tests
+fn rdate_env() -> Command {
+    let mut command = Command::cargo_bin("rdate").unwrap();
+    command
+        .env("TZ", "UTC")
+        .env("LC_ALL", "C")
+        .env_remove("LC_TIME")
+        .env_remove("LANG")
+        .env_remove("SOURCE_DATE_EPOCH");
+    command
+}
+
+/// rdate with the clock fixed at NOW
+fn rdate() -> Command {
+    let mut command = rdate_env();
+    command.args(["--now", NOW]);
+    command
+}
+
+// Expected outputs match GNU coreutils date with TZ=UTC at the same time
+#[test_case(&[], "Fri Mar  1 09:05:07 UTC 2024"; "default format")]
+#[test_case(&["-d", "2024-12-30"], "Mon Dec 30 00:00:00 UTC 2024"; "default format in iso week of next year")]
+#[test_case(&["+%F %T %s"], "2024-03-01 09:05:07 1709283907"; "format operand")]
+#[test_case(&["-d", "2 days ago"], "Wed Feb 28 09:05:07 UTC 2024"; "relative date")]
+#[test_case(&["-d", "tomorrow", "+%A"], "Saturday"; "relative day")]
+#[test_case(&["-v", "+1m", "-v", "-1d", "+%F"], "2024-03-31"; "adjustments")]
+#[test_case(&["-I"], "2024-03-01"; "iso 8601")]
+#[test_case(&["-Iseconds"], "2024-03-01T09:05:07+00:00"; "iso 8601 seconds")]
+#[test_case(&["-uIminutes"], "2024-03-01T09:05+00:00"; "iso 8601 after a flag")]
+#[test_case(&["-I", "add", "now", "1d"], "2024-03-02"; "iso 8601 before a subcommand")]
+#[test_case(&["-R"], "Fri, 01 Mar 2024 09:05:07 +0000"; "rfc 2822")]
+#[test_case(&["--rfc-3339=seconds"], "2024-03-01 09:05:07+00:00"; "rfc 3339")]
+#[test_case(&["-u", "+%H:%M"], "09:05"; "utc")]
+#[test_case(&["--tz", "Europe/Paris"], "Fri Mar  1 10:05:07 CET 2024"; "time zone")]
+#[test_case(&["--locale", "fr_FR", "+%A %d %B"], "vendredi 01 mars"; "locale")]
+#[test_case(&["diff", "2024-01-01", "now"], "5216707"; "diff in seconds")]
+#[test_case(&["diff", "2024-01-01", "now", "-o", "human"], "2 months 9 hours 5 minutes 7 seconds"; "diff in words")]
+#[test_case(&["add", "now", "1 month", "+%F"], "2024-04-01"; "add")]
+#[test_case(&["add", "now", "-1d", "+%F"], "2024-02-29"; "add negative")]
+fn test_now(args: &[&str], expected_result: &str) {
+    rdate()
+        .args(args)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", expected_result))
+        .stderr("");
+}
+
+#[test]
+fn test_now_file() {
+    rdate()
+        .args(["-f", "test_data/test_dates.txt", "+%F"])
+        .assert()
+        .success()
+        .stdout("2024-03-01\n1970-01-01\n2024-02-28\n")
+        .stderr("Error: line 3: invalid date provided: not a date\n");
+}
+
+#[test]
+fn test_now_stdin() {
+    rdate()
+        .args(["-f", "-", "+%F"])
+        .write_stdin("yesterday\nnext month\n")
+        .assert()
+        .success()
+        .stdout("2024-02-29\n2024-04-01\n");
+}
+
+#[test]
+fn test_now_stdin_errors_in_order() {
+    rdate()
+        .args(["-f", "-", "+%F"])
+        .write_stdin("@0\nsoon\n\n@86400\n")
+        .assert()
+        .success()
+        .stdout("1970-01-01\n1970-01-02\n")
+        .stderr("Error: line 2: invalid date provided: soon\n");
+}
+
+// Each Line Is Written As Soon As It Is Read, So A Stream That Stays Open Still Gets Answers
+#[test]
+fn test_now_stdin_streamed() {
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rdate"))
+        .args(["--now", NOW, "-u", "-f", "-", "+%F"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in ["@0\n", "@86400\n"] {
+            stdin.write_all(line.as_bytes()).unwrap();
+            let mut output = String::new();
+            stdout.read_line(&mut output).unwrap();
+            sender.send(output).unwrap();
+        }
+    });
+
+    for expected_result in ["1970-01-01\n", "1970-01-02\n"] {
+        let output = receiver.recv_timeout(std::time::Duration::from_secs(10));
+        assert_eq!(Ok(expected_result.to_string()), output);
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+// The TIMESPEC Must Be Attached, So A Separate Word Is The Format Operand
+#[test]
+fn test_iso_8601_separate_timespec() {
+    rdate()
+        .args(["-I", "seconds"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with '<FORMAT>'"));
+}
+
+#[test_case(&[], "0", "Thu Jan  1 00:00:00 UTC 1970"; "source date epoch")]
+#[test_case(&["-d", "1 hour ago", "+%s"], "7200", "3600"; "relative to source date epoch")]
+#[test_case(&["--now", "@86400.5", "+%F %3N"], "0", "1970-01-02 500"; "now wins over source date epoch")]
+#[test_case(&["--now", "2000-01-01 12:00 +0100", "-Iminutes"], "", "2000-01-01T11:00+00:00"; "now as a date")]
+fn test_source_date_epoch(args: &[&str], source_date_epoch: &str, expected_result: &str) {
+    rdate_env()
+        .env("SOURCE_DATE_EPOCH", source_date_epoch)
+        .args(args)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", expected_result));
+}
+
+#[test_case(&["--now", "soon"], "", "Error: invalid date provided: soon\n"; "invalid now")]
+#[test_case(&[], "1e9", "Error: invalid SOURCE_DATE_EPOCH provided: 1e9 (expected seconds since the epoch)\n"; "invalid source date epoch")]
+fn test_invalid_clock(args: &[&str], source_date_epoch: &str, expected_error: &str) {
+    rdate_env()
+        .env("SOURCE_DATE_EPOCH", source_date_epoch)
+        .args(args)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(expected_error.to_string());
+}
+
+// An Unknown Locale From The Environment Falls Back To POSIX, While --locale Must Be Known
+#[test_case("LANG", "en", &[], "Fri Mar  1 09:05:07 UTC 2024\n", ""; "unknown lang")]
+#[test_case("LC_TIME", "xx_YY.UTF-8", &["+%A"], "Friday\n", ""; "unknown lc time")]
+#[test_case("LANG", "fr_FR.UTF-8", &["+%A"], "vendredi\n", ""; "known lang")]
+#[test_case("LANG", "fr_FR.UTF-8", &["--locale", "en"], "", "Error: invalid locale provided: en (expected a name such as fr_FR)\n"; "unknown locale option")]
+fn test_environment_locale(
+    name: &str,
+    value: &str,
+    args: &[&str],
+    expected_result: &str,
+    expected_error: &str,
+) {
+    rdate()
+        .env_remove("LC_ALL")
+        .env(name, value)
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected_result.to_string())
+        .stderr(expected_error.to_string());
+}
+
+// add Stops At The End Of A Shorter Month As -v Does, While -d Overflows As In date(1)
+#[test_case(&["add", "2024-01-31", "1 month", "+%F"], "2024-02-29"; "add clamps")]
+#[test_case(&["-v", "+1m", "-d", "2024-01-31", "+%F"], "2024-02-29"; "adjustment clamps")]
+#[test_case(&["-d", "2024-01-31 1 month", "+%F"], "2024-03-02"; "date overflows")]
+fn test_month_arithmetic(args: &[&str], expected_result: &str) {
+    rdate()
+        .args(args)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", expected_result));
+}
+
+// Help Is Wrapped To The Terminal, So Compare It With Whitespace Collapsed
+#[test_case(&["--help"], "\"2024-01-31 1 month\" is Mar 2, unlike add and -v"; "date help")]
+#[test_case(&["add", "--help"], "plus 1 month is Feb 29. -d \"2024-01-31 1 month\" overflows to Mar 2"; "add help")]
+fn test_month_arithmetic_help(args: &[&str], expected_result: &str) {
+    let expected_result = expected_result.to_string();
+
+    rdate()
+        .args(args)
+        .assert()
+        .success()
+        .stdout(predicate::function(move |help: &str| {
+            help.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .contains(&expected_result)
+        }));
+}
+
+#[test]
+fn test_now_hidden() {
+    rdate()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--now").not());
+}
+
+/// Starts `server` on a free port and queries it with `client` until it is listening
+fn serve_and_query(
+    server: &mut std::process::Command,
+    mut client: impl FnMut() -> Command,
+    transport: &[&str],
+) -> String {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+
+    let mut server = server
+        .args(["--serve", "--port", &port.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Ask Again Until The Server Is Listening
+    let address = format!("127.0.0.1:{}", port);
+    let output = (0..50).find_map(|_| {
+        let output = client()
+            .args(["--query", &address])
+            .args(transport)
+            .output()
+            .unwrap();
+        match output.stdout.is_empty() {
+            true => {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                None
+            }
+            false => Some(output),
+        }
+    });
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    String::from_utf8(output.unwrap().stdout).unwrap()
+}
+
+// A Server And Client On Different Fixed Clocks See An Exact Offset
+#[test_case(&[]; "tcp")]
+#[test_case(&["--udp"]; "udp")]
+fn test_serve_and_query(transport: &[&str]) {
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("rdate"));
+    server.args(["--now", "1709283967"]);
+
+    assert_eq!(
+        "Fri Mar  1 09:06:07 UTC 2024\noffset +60 seconds\n",
+        serve_and_query(&mut server, rdate, transport)
+    );
+}
+
+// Both Ends Ignore SOURCE_DATE_EPOCH, So The Same System Clock Is At Most A Second Apart
+#[test]
+fn test_serve_and_query_ignore_source_date_epoch() {
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("rdate"));
+    server.env("SOURCE_DATE_EPOCH", "0");
+    let client = || {
+        let mut command = rdate_env();
+        command.env("SOURCE_DATE_EPOCH", "0").arg("+%Y");
+        command
+    };
+
+    let output = serve_and_query(&mut server, client, &[]);
+    let (year, offset) = output.split_once('\n').unwrap();
+
+    assert_ne!("1970", year);
+    assert!([
+        "offset -1 seconds\n",
+        "offset +0 seconds\n",
+        "offset +1 seconds\n"
+    ]
+    .contains(&offset));
+}
+
+// Piped Output Is Not A Terminal, So Today Is Not Highlighted
+#[test]
+fn test_cal() {
+    rdate()
+        .args(["cal", "-m"])
+        .assert()
+        .success()
+        .stdout(
+            "     March 2024\nMo Tu We Th Fr Sa Su\n             1  2  3\n 4  5  6  7  8  9 10\n11 12 13 14 15 16 17\n18 19 20 21 22 23 24\n25 26 27 28 29 30 31\n",
+        );
+}
+
+#[test_case(&["cal", "-3", "-y"], "cannot be used with"; "three months and year")]
+#[test_case(&["cal", "1", "2", "3"], "wasn't expecting any more values"; "too many operands")]
+fn test_cal_invalid(args: &[&str], expected_error: &str) {
+    rdate()
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected_error));
+}
+
+// Piped Output Gets A Line Per Second Rather Than Redrawing One
+#[test_case(&["countdown", "0s"], "0:00:00\n"; "countdown at zero")]
+#[test_case(&["countdown", "1s"], "0:00:01\n0:00:00\n"; "countdown")]
+#[test_case(&["until", "09:05:08"], "0:00:01\n0:00:00\n"; "until")]
+#[test_case(&["until", "yesterday"], "0:00:00\n"; "until a time already passed")]
+fn test_timer(args: &[&str], expected_result: &str) {
+    rdate()
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected_result.to_string())
+        .stderr("");
+}
+
+// Timers Tick In Real Time, So They Ignore SOURCE_DATE_EPOCH And Find 1970 Long Gone
+#[test_case("0"; "source date epoch")]
+#[test_case("1e9"; "invalid source date epoch")]
+fn test_timer_source_date_epoch(source_date_epoch: &str) {
+    rdate_env()
+        .env("SOURCE_DATE_EPOCH", source_date_epoch)
+        .args(["until", "1970-01-01 00:00:01"])
+        .assert()
+        .success()
+        .stdout("0:00:00\n")
+        .stderr("");
+}
+
+#[test]
+fn test_stopwatch_without_input() {
+    rdate()
+        .arg("stopwatch")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("0:00:00\ntotal 0:00:00.0\n");
+}
+
+#[test_case(&["countdown", "-5m"], "Error: invalid duration provided: -5m\n"; "negative countdown")]
+#[test_case(&["until", "teatime"], "Error: invalid date provided: teatime\n"; "invalid until")]
+fn test_timer_invalid(args: &[&str], expected_error: &str) {
+    rdate()
+        .args(args)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(expected_error.to_string());
+}
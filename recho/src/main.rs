@@ -1,44 +1,472 @@
 use clap::Parser;
+use std::io::{self, Read, Write};
+use style::Style;
 
-// TODO: Add stdin support
-// TODO: Add env variables support
+mod printf;
+mod quote;
+mod repeat;
+mod style;
 
 #[cfg(test)]
 mod tests;
 
 #[derive(Parser)]
+#[clap(allow_negative_numbers = true)]
 struct Cli {
     /// Values to print
-    #[clap(required = true, multiple = true)]
+    #[clap(multiple = true)]
     values: Vec<String>,
+
+    /// Do not output the trailing newline
+    #[clap(short = 'n', action)]
+    no_newline: bool,
+
+    /// Enable interpretation of backslash escapes
+    #[clap(short = 'e', action, overrides_with = "no-escapes")]
+    escapes: bool,
+
+    /// Disable interpretation of backslash escapes (default)
+    #[clap(short = 'E', action, overrides_with = "escapes")]
+    no_escapes: bool,
+
+    /// Read the value to print from stdin instead of the arguments
+    #[clap(long = "stdin", action, conflicts_with = "values")]
+    stdin: bool,
+
+    /// Expand $VAR, ${VAR} and ${VAR:-default} from the environment
+    #[clap(long = "expand", action)]
+    expand: bool,
+
+    /// Fail on unset variables instead of expanding them to nothing
+    #[clap(long = "strict", action, requires = "expand")]
+    strict: bool,
+
+    /// Format the values with a printf FORMAT instead of echoing them
+    #[clap(
+        long = "printf",
+        value_name = "FORMAT",
+        allow_hyphen_values = true,
+        conflicts_with_all = &[
+            "no-newline",
+            "escapes",
+            "stdin",
+            "expand",
+            "json",
+            "shell-quote",
+            "csv"
+        ]
+    )]
+    printf: Option<String>,
+
+    /// Print the values as a JSON array of strings
+    #[clap(long = "json", action, conflicts_with_all = &["shell-quote", "csv"])]
+    json: bool,
+
+    /// Print the values joined with spaces as a single JSON string
+    #[clap(long = "join", action, requires = "json")]
+    join: bool,
+
+    /// Quote each value as a POSIX sh word
+    #[clap(long = "shell-quote", action, conflicts_with = "csv")]
+    shell_quote: bool,
+
+    /// Print the values as one RFC 4180 CSV row, ended by CRLF
+    #[clap(long = "csv", action)]
+    csv: bool,
+
+    /// Color the output: auto, always, never, a color name, a 0-255 index or #RRGGBB
+    #[clap(long = "color", value_name = "COLOR", multiple_occurrences = true)]
+    color: Vec<String>,
+
+    /// Comma separated text styles: bold, dim, italic, underline, blink, reverse, strikethrough
+    #[clap(
+        long = "style",
+        value_name = "STYLE",
+        multiple_occurrences = true,
+        use_value_delimiter = false
+    )]
+    style: Vec<String>,
+
+    /// Print the output in bold
+    #[clap(long = "bold", action)]
+    bold: bool,
+
+    /// Print the output in italics
+    #[clap(long = "italic", action)]
+    italic: bool,
+
+    /// Underline the output
+    #[clap(long = "underline", action)]
+    underline: bool,
+
+    /// Print the output repeatedly, forever or N times ("y" when no values are given)
+    #[clap(
+        long = "repeat",
+        value_name = "N",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true
+    )]
+    repeat: Option<Option<u64>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CustomError {
-    EmptyValues,
+    NoInput,
+    UnsetVariable(String),
+    InvalidFormat(String),
+    InvalidFieldWidth(String),
+    InvalidPrecision(String),
+    InvalidNumber(String),
+    NumberNotConverted(String),
+    NumberOutOfRange(String),
+    InvalidColor(String),
+    InvalidStyle(String),
+    WriteFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expansion {
+    Disabled,
+    Enabled,
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Echo,
+    JsonArray,
+    JsonString,
+    ShellQuote,
+    Csv,
 }
 
 fn main() -> Result<(), CustomError> {
     let args = Cli::parse();
+    let repeat = args.repeat;
 
-    let result = process_echo(args.values);
-    match result {
-        Ok(result) => println!("{}", result),
-        Err(error) => eprintln!("Error: {}", parse_custom_error(error)),
+    let result = process_args(args).and_then(|result| match repeat {
+        Some(count) => repeat::write_repeated(&result, count, &mut io::stdout().lock()),
+        None => {
+            io::stdout().write_all(&result).unwrap_or(());
+            Ok(())
+        }
+    });
+    if let Err(error) = result {
+        eprintln!("Error: {}", parse_custom_error(error));
     }
 
     Ok(())
 }
 
+fn process_args(mut args: Cli) -> Result<Vec<u8>, CustomError> {
+    if args.repeat.is_some() && args.values.is_empty() && !args.stdin && args.printf.is_none() {
+        args.values.push("y".to_string());
+    }
+
+    let expansion = match (args.expand, args.strict) {
+        (false, _) => Expansion::Disabled,
+        (true, false) => Expansion::Enabled,
+        (true, true) => Expansion::Strict,
+    };
+    let output = match (args.json, args.join, args.shell_quote, args.csv) {
+        (true, false, _, _) => Output::JsonArray,
+        (true, true, _, _) => Output::JsonString,
+        (_, _, true, _) => Output::ShellQuote,
+        (_, _, _, true) => Output::Csv,
+        _ => Output::Echo,
+    };
+
+    let (mode, style) = style::parse_style(
+        &args.color,
+        &args.style,
+        args.bold,
+        args.italic,
+        args.underline,
+    )?;
+    let is_styled = style::is_style_enabled(
+        mode,
+        atty::is(atty::Stream::Stdout),
+        std::env::var_os("NO_COLOR"),
+        std::env::var_os("FORCE_COLOR"),
+    );
+    let style = match is_styled {
+        true => style,
+        false => Style::default(),
+    };
+
+    match (args.printf, args.stdin) {
+        (Some(format), _) => printf::process_printf(&format, &args.values, &mut io::stderr())
+            .map(|result| style.paint(result)),
+        (None, true) => process_stdin().and_then(|values| {
+            process_echo(
+                values,
+                !args.no_newline,
+                args.escapes,
+                expansion,
+                output,
+                &style,
+            )
+        }),
+        (None, false) => process_echo(
+            args.values,
+            !args.no_newline,
+            args.escapes,
+            expansion,
+            output,
+            &style,
+        ),
+    }
+}
+
 fn parse_custom_error(error: CustomError) -> String {
     match error {
-        CustomError::EmptyValues => "empty values provided".to_string(),
+        CustomError::NoInput => "no input provided".to_string(),
+        CustomError::UnsetVariable(name) => format!("unset variable provided: {}", name),
+        CustomError::InvalidFormat(spec) => format!("invalid conversion specification: {}", spec),
+        CustomError::InvalidFieldWidth(width) => format!("invalid field width provided: {}", width),
+        CustomError::InvalidPrecision(precision) => {
+            format!("invalid precision provided: {}", precision)
+        }
+        CustomError::InvalidNumber(value) => format!("invalid number provided: {}", value),
+        CustomError::NumberNotConverted(value) => {
+            format!("number not completely converted: {}", value)
+        }
+        CustomError::NumberOutOfRange(value) => format!("number out of range provided: {}", value),
+        CustomError::InvalidColor(color) => format!("invalid color provided: {}", color),
+        CustomError::InvalidStyle(style) => format!("invalid style provided: {}", style),
+        CustomError::WriteFailed => "failed to write output".to_string(),
     }
 }
 
-fn process_echo(values: Vec<String>) -> Result<String, CustomError> {
-    if values.is_empty() {
-        return Err(CustomError::EmptyValues);
+fn process_stdin() -> Result<Vec<String>, CustomError> {
+    if atty::is(atty::Stream::Stdin) {
+        return Err(CustomError::NoInput);
     }
-    Ok(values.join("\n"))
+
+    let mut buf = vec![];
+    io::stdin()
+        .read_to_end(&mut buf)
+        .map_err(|_| CustomError::NoInput)?;
+
+    // The Piped Newline Is Replaced By Our Own Trailing Newline
+    let mut value = String::from_utf8_lossy(&buf).to_string();
+    if value.ends_with('\n') {
+        value.pop();
+    }
+
+    Ok(vec![value])
+}
+
+fn process_echo(
+    values: Vec<String>,
+    trailing_newline: bool,
+    escapes: bool,
+    expansion: Expansion,
+    output: Output,
+    style: &Style,
+) -> Result<Vec<u8>, CustomError> {
+    // Structured Modes Keep The Values Apart So Each One Is Quoted On Its Own
+    let values = match output {
+        Output::Echo | Output::JsonString => vec![values.join(" ")],
+        _ => values,
+    };
+
+    let mut fields = Vec::with_capacity(values.len());
+    let mut is_stopped = false;
+    for mut value in values {
+        if expansion != Expansion::Disabled {
+            let is_strict = expansion == Expansion::Strict;
+            value = expand_variables(&value, is_strict, |name| std::env::var(name).ok())?;
+        }
+
+        let (field, is_field_stopped) = match escapes {
+            true => process_escapes(&value, true),
+            false => (value.into_bytes(), false),
+        };
+        fields.push(field);
+
+        if is_field_stopped {
+            is_stopped = true;
+            break;
+        }
+    }
+
+    let result = match output {
+        Output::Echo => fields.concat(),
+        Output::JsonArray => quote::json_array(&fields),
+        Output::JsonString => quote::json_string(&fields.concat()),
+        Output::ShellQuote => quote::shell_words(&fields),
+        Output::Csv => quote::csv_row(&fields),
+    };
+
+    // The Newline Stays Outside The Style So The Reset Lands On The Same Line
+    let mut result = style.paint(result);
+
+    // \c Suppresses All Further Output Including The Newline
+    if trailing_newline && !is_stopped {
+        match output {
+            Output::Csv => result.extend_from_slice(b"\r\n"),
+            _ => result.push(b'\n'),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Expands backslash escapes, returning the bytes and whether `\c` stopped the output.
+/// Octal escapes are `\0NNN` as in echo when `zero_octal` is set, otherwise `\NNN` as in printf.
+fn process_escapes(value: &str, zero_octal: bool) -> (Vec<u8>, bool) {
+    let mut result = vec![];
+    let mut rest = value;
+
+    while let Some(index) = rest.find('\\') {
+        result.extend_from_slice(&rest.as_bytes()[..index]);
+        rest = &rest[index + 1..];
+
+        let escape = match rest.chars().next() {
+            Some(escape) => escape,
+            None => {
+                result.push(b'\\');
+                break;
+            }
+        };
+        let escaped = rest;
+        rest = &rest[escape.len_utf8()..];
+
+        match escape {
+            'a' => result.push(0x07),
+            'b' => result.push(0x08),
+            'c' => return (result, true),
+            'e' => result.push(0x1b),
+            'f' => result.push(0x0c),
+            'n' => result.push(b'\n'),
+            'r' => result.push(b'\r'),
+            't' => result.push(b'\t'),
+            'v' => result.push(0x0b),
+            '\\' => result.push(b'\\'),
+            '0' if zero_octal => {
+                let (code, length) = parse_digits(rest, 8, 3);
+                result.push(code as u8);
+                rest = &rest[length..];
+            }
+            '0'..='7' => {
+                let (code, length) = parse_digits(escaped, 8, 3);
+                result.push(code as u8);
+                rest = &escaped[length..];
+            }
+            'x' => match parse_digits(rest, 16, 2) {
+                (_, 0) => result.extend_from_slice(b"\\x"),
+                (code, length) => {
+                    result.push(code as u8);
+                    rest = &rest[length..];
+                }
+            },
+            'u' | 'U' => {
+                let max_digits = if escape == 'u' { 4 } else { 8 };
+                let (code, length) = parse_digits(rest, 16, max_digits);
+
+                match char::from_u32(code).filter(|_| length > 0) {
+                    Some(character) => {
+                        let mut buf = [0; 4];
+                        result.extend_from_slice(character.encode_utf8(&mut buf).as_bytes());
+                        rest = &rest[length..];
+                    }
+                    None => {
+                        result.push(b'\\');
+                        result.push(escape as u8);
+                    }
+                }
+            }
+            _ => {
+                result.push(b'\\');
+                let mut buf = [0; 4];
+                result.extend_from_slice(escape.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+
+    result.extend_from_slice(rest.as_bytes());
+    (result, false)
+}
+
+/// Expands `$VAR`, `${VAR}` and `${VAR:-default}`, leaving any other `$` as is
+fn expand_variables<F: Fn(&str) -> Option<String>>(
+    value: &str,
+    is_strict: bool,
+    lookup: F,
+) -> Result<String, CustomError> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let (name, default, length) = match parse_variable(rest) {
+            Some(variable) => variable,
+            None => {
+                result.push('$');
+                continue;
+            }
+        };
+        rest = &rest[length..];
+
+        let variable = lookup(name);
+        match (variable, default) {
+            (Some(variable), Some(default)) if variable.is_empty() => result.push_str(default),
+            (Some(variable), _) => result.push_str(&variable),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) if is_strict => return Err(CustomError::UnsetVariable(name.to_string())),
+            (None, None) => (),
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Parses the variable after a `$`, returning its name, default and length
+fn parse_variable(value: &str) -> Option<(&str, Option<&str>, usize)> {
+    if let Some(braced) = value.strip_prefix('{') {
+        let end = braced.find('}')?;
+        let (name, default) = match braced[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&braced[..end], None),
+        };
+
+        return match is_variable_name(name) {
+            true => Some((name, default, end + 2)),
+            false => None,
+        };
+    }
+
+    let length = value
+        .find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))
+        .unwrap_or(value.len());
+
+    match is_variable_name(&value[..length]) {
+        true => Some((&value[..length], None, length)),
+        false => None,
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut characters = name.chars();
+    match characters.next() {
+        Some(character) if character.is_ascii_alphabetic() || character == '_' => characters
+            .all(|character| character.is_ascii_alphanumeric() || character == '_'),
+        _ => false,
+    }
+}
+
+/// Parses up to `max_digits` leading digits, returning the value and how many were used
+fn parse_digits(value: &str, radix: u32, max_digits: usize) -> (u32, usize) {
+    value
+        .chars()
+        .take(max_digits)
+        .map_while(|character| character.to_digit(radix))
+        .fold((0, 0), |(code, length), digit| {
+            (code * radix + digit, length + 1)
+        })
 }
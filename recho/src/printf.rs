@@ -0,0 +1,599 @@
+use std::io::Write;
+
+use crate::{parse_custom_error, process_escapes, CustomError};
+
+/// The largest width or precision, as coreutils allows, so a huge one fails before it is allocated
+const MAX_COUNT: usize = i32::MAX as usize;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Flags {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Count {
+    Fixed(usize),
+    Argument,
+}
+
+#[derive(Debug, PartialEq)]
+struct Spec {
+    flags: Flags,
+    width: Option<Count>,
+    precision: Option<Count>,
+    conversion: char,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Literal(&'a str),
+    Percent,
+    Conversion(Spec),
+}
+
+/// A number as far as an argument could be read, with the error for the rest of it
+type Parsed<T> = (T, Option<CustomError>);
+
+/// Formats `arguments` with `format` as POSIX printf does, reusing the format for surplus arguments.
+/// Like coreutils, an argument that is not a number is reported to `errors` and printed as far as
+/// it could be read, and the remaining arguments are still printed.
+pub fn process_printf<E: Write>(
+    format: &str,
+    arguments: &[String],
+    errors: &mut E,
+) -> Result<Vec<u8>, CustomError> {
+    let tokens = parse_format(format)?;
+    let mut result = vec![];
+    let mut arguments = Arguments {
+        values: arguments,
+        index: 0,
+    };
+
+    loop {
+        let start_index = arguments.index;
+
+        if format_tokens(&tokens, &mut arguments, &mut result, errors)? {
+            break;
+        }
+
+        // Stop Once Arguments Run Out Or The Format Consumes None
+        if arguments.index >= arguments.values.len() || arguments.index == start_index {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+struct Arguments<'a> {
+    values: &'a [String],
+    index: usize,
+}
+
+impl<'a> Arguments<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let value = self.values.get(self.index)?;
+        self.index += 1;
+        Some(value)
+    }
+}
+
+fn parse_format(format: &str) -> Result<Vec<Token<'_>>, CustomError> {
+    let mut tokens = vec![];
+    let mut rest = format;
+
+    while let Some(index) = rest.find('%') {
+        if index > 0 {
+            tokens.push(Token::Literal(&rest[..index]));
+        }
+
+        let (token, length) = parse_conversion(&rest[index..])?;
+        tokens.push(token);
+        rest = &rest[index + length..];
+    }
+
+    if !rest.is_empty() {
+        tokens.push(Token::Literal(rest));
+    }
+
+    Ok(tokens)
+}
+
+/// Parses `%[flags][width][.precision]conversion` at the start of `value`, returning its length
+fn parse_conversion(value: &str) -> Result<(Token<'_>, usize), CustomError> {
+    let bytes = value.as_bytes();
+    let mut index = 1;
+    let mut flags = Flags::default();
+
+    if bytes.get(index) == Some(&b'%') {
+        return Ok((Token::Percent, 2));
+    }
+
+    while let Some(flag) = bytes.get(index) {
+        match flag {
+            b'-' => flags.left = true,
+            b'+' => flags.plus = true,
+            b' ' => flags.space = true,
+            b'#' => flags.alternate = true,
+            b'0' => flags.zero = true,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    let width = parse_count(bytes, &mut index, CustomError::InvalidFieldWidth)?;
+    let precision = match bytes.get(index) {
+        Some(b'.') => {
+            index += 1;
+            let precision = parse_count(bytes, &mut index, CustomError::InvalidPrecision)?;
+            Some(precision.unwrap_or(Count::Fixed(0)))
+        }
+        _ => None,
+    };
+
+    let conversion = value[index..].chars().next();
+    match conversion {
+        Some(
+            's' | 'b' | 'c' | 'd' | 'i' | 'u' | 'o' | 'x' | 'X' | 'f' | 'F' | 'e' | 'E' | 'g' | 'G',
+        ) => {
+            let spec = Spec {
+                flags,
+                width,
+                precision,
+                conversion: conversion.unwrap(),
+            };
+            Ok((Token::Conversion(spec), index + 1))
+        }
+        _ => {
+            let length = index + conversion.map_or(0, |conversion| conversion.len_utf8());
+            Err(CustomError::InvalidFormat(value[..length].to_string()))
+        }
+    }
+}
+
+fn parse_count(
+    bytes: &[u8],
+    index: &mut usize,
+    invalid: fn(String) -> CustomError,
+) -> Result<Option<Count>, CustomError> {
+    if bytes.get(*index) == Some(&b'*') {
+        *index += 1;
+        return Ok(Some(Count::Argument));
+    }
+
+    let start = *index;
+    while bytes.get(*index).is_some_and(u8::is_ascii_digit) {
+        *index += 1;
+    }
+
+    let digits = std::str::from_utf8(&bytes[start..*index]).unwrap_or_default();
+    if digits.is_empty() {
+        return Ok(None);
+    }
+
+    match digits.parse::<usize>() {
+        Ok(count) if count <= MAX_COUNT => Ok(Some(Count::Fixed(count))),
+        _ => Err(invalid(digits.to_string())),
+    }
+}
+
+/// Writes one pass over the format, returning whether `\c` stopped all further output
+fn format_tokens<E: Write>(
+    tokens: &[Token],
+    arguments: &mut Arguments,
+    result: &mut Vec<u8>,
+    errors: &mut E,
+) -> Result<bool, CustomError> {
+    for token in tokens {
+        match token {
+            Token::Literal(literal) => {
+                let (bytes, is_stopped) = process_escapes(literal, false);
+                result.extend_from_slice(&bytes);
+                if is_stopped {
+                    return Ok(true);
+                }
+            }
+            Token::Percent => result.push(b'%'),
+            Token::Conversion(spec) => {
+                if format_conversion(spec, arguments, result, errors)? {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn format_conversion<E: Write>(
+    spec: &Spec,
+    arguments: &mut Arguments,
+    result: &mut Vec<u8>,
+    errors: &mut E,
+) -> Result<bool, CustomError> {
+    let mut flags = spec.flags;
+
+    let width = match spec.width {
+        Some(Count::Argument) => {
+            let argument = arguments.next().unwrap_or("");
+            let width = report(parse_integer(argument), errors)?;
+            if width.unsigned_abs() > MAX_COUNT as u128 {
+                return Err(CustomError::InvalidFieldWidth(argument.to_string()));
+            }
+            flags.left |= width < 0;
+            Some(width.unsigned_abs() as usize)
+        }
+        Some(Count::Fixed(width)) => Some(width),
+        None => None,
+    };
+
+    // A Negative Precision Argument Counts As No Precision
+    let precision = match spec.precision {
+        Some(Count::Argument) => {
+            let argument = arguments.next().unwrap_or("");
+            let precision = report(parse_integer(argument), errors)?;
+            if precision > MAX_COUNT as i128 {
+                return Err(CustomError::InvalidPrecision(argument.to_string()));
+            }
+            usize::try_from(precision).ok()
+        }
+        Some(Count::Fixed(precision)) => Some(precision),
+        None => None,
+    };
+
+    let argument = arguments.next().unwrap_or("");
+    let mut is_stopped = false;
+
+    let (prefix, body, is_numeric) = match spec.conversion {
+        's' => (vec![], truncate(argument.as_bytes(), precision), false),
+        'b' => {
+            let (bytes, stopped) = process_escapes(argument, true);
+            is_stopped = stopped;
+            (vec![], truncate(&bytes, precision), false)
+        }
+        'c' => {
+            // Only The First Byte Is Printed, And An Empty Argument Prints A NUL Byte
+            let byte = argument.as_bytes().first().copied().unwrap_or(0);
+            (vec![], vec![byte], false)
+        }
+        'd' | 'i' => {
+            let value = report(parse_integer(argument), errors)?;
+            let value = report(to_signed(value, argument), errors)?;
+            let sign = sign(value < 0, &flags);
+            let digits = pad_digits(value.unsigned_abs().to_string(), precision);
+            (sign.into_bytes(), digits.into_bytes(), true)
+        }
+        'u' | 'o' | 'x' | 'X' => {
+            let value = report(parse_integer(argument), errors)?;
+            let value = report(to_unsigned(value, argument), errors)?;
+            let (prefix, digits) = format_unsigned(value, spec.conversion, &flags, precision);
+            (prefix.into_bytes(), digits.into_bytes(), true)
+        }
+        _ => {
+            let value = report(parse_float(argument), errors)?;
+            let sign = sign(value.is_sign_negative() && !value.is_nan(), &flags);
+            if !value.is_finite() {
+                flags.zero = false;
+            }
+            let body = format_float(value.abs(), spec.conversion, &flags, precision);
+            (sign.into_bytes(), body.into_bytes(), true)
+        }
+    };
+
+    // Integer Precision Replaces Zero Padding
+    let is_integer = matches!(spec.conversion, 'd' | 'i' | 'u' | 'o' | 'x' | 'X');
+    let is_zero_padded = flags.zero && is_numeric && !(is_integer && precision.is_some());
+
+    result.extend(pad(&prefix, &body, width, flags.left, is_zero_padded));
+    Ok(is_stopped)
+}
+
+/// Writes the error for a number that was not completely read, keeping the value read so far
+fn report<T, E: Write>((value, error): Parsed<T>, errors: &mut E) -> Result<T, CustomError> {
+    if let Some(error) = error {
+        writeln!(errors, "Error: {}", parse_custom_error(error))
+            .map_err(|_| CustomError::WriteFailed)?;
+    }
+
+    Ok(value)
+}
+
+/// Values past the range of `%d` are clamped to it
+fn to_signed(value: i128, argument: &str) -> Parsed<i64> {
+    match i64::try_from(value) {
+        Ok(value) => (value, None),
+        Err(_) => {
+            let value = if value < 0 { i64::MIN } else { i64::MAX };
+            (value, Some(out_of_range(argument)))
+        }
+    }
+}
+
+/// Negative values wrap around as in C, and values past the range are clamped to it
+fn to_unsigned(value: i128, argument: &str) -> Parsed<u64> {
+    match value.unsigned_abs() <= u64::MAX as u128 {
+        true => (value as u64, None),
+        false => (u64::MAX, Some(out_of_range(argument))),
+    }
+}
+
+fn out_of_range(argument: &str) -> CustomError {
+    CustomError::NumberOutOfRange(argument.to_string())
+}
+
+fn sign(is_negative: bool, flags: &Flags) -> String {
+    match (is_negative, flags.plus, flags.space) {
+        (true, _, _) => "-".to_string(),
+        (false, true, _) => "+".to_string(),
+        (false, false, true) => " ".to_string(),
+        _ => "".to_string(),
+    }
+}
+
+fn truncate(bytes: &[u8], precision: Option<usize>) -> Vec<u8> {
+    match precision {
+        Some(precision) if precision < bytes.len() => bytes[..precision].to_vec(),
+        _ => bytes.to_vec(),
+    }
+}
+
+fn pad_digits(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(0) if digits == "0" => "".to_string(),
+        Some(precision) if precision > digits.len() => {
+            "0".repeat(precision - digits.len()) + &digits
+        }
+        _ => digits,
+    }
+}
+
+fn format_unsigned(
+    value: u64,
+    conversion: char,
+    flags: &Flags,
+    precision: Option<usize>,
+) -> (String, String) {
+    let digits = match conversion {
+        'o' => format!("{:o}", value),
+        'x' => format!("{:x}", value),
+        'X' => format!("{:X}", value),
+        _ => value.to_string(),
+    };
+    let mut digits = pad_digits(digits, precision);
+
+    let prefix = match (conversion, flags.alternate && value != 0) {
+        ('x', true) => "0x",
+        ('X', true) => "0X",
+        _ => "",
+    };
+
+    if conversion == 'o' && flags.alternate && !digits.starts_with('0') {
+        digits.insert(0, '0');
+    }
+
+    (prefix.to_string(), digits)
+}
+
+fn format_float(value: f64, conversion: char, flags: &Flags, precision: Option<usize>) -> String {
+    let precision = precision.unwrap_or(6);
+    let is_upper = conversion.is_ascii_uppercase();
+
+    let body = if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        "inf".to_string()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'e' => format_exponent(value, precision, flags.alternate),
+            'g' => format_general(value, precision, flags.alternate),
+            _ => match flags.alternate && precision == 0 {
+                true => format!("{:.0}.", value),
+                false => format!("{:.*}", precision, value),
+            },
+        }
+    };
+
+    match is_upper {
+        true => body.to_ascii_uppercase(),
+        false => body,
+    }
+}
+
+/// C style `d.ddde±dd`, as Rust omits the exponent sign and padding
+fn format_exponent(value: f64, precision: usize, alternate: bool) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    let point = if alternate && precision == 0 { "." } else { "" };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+
+    format!(
+        "{}{}e{}{:02}",
+        mantissa,
+        point,
+        exponent_sign,
+        exponent.abs()
+    )
+}
+
+fn format_general(value: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let formatted = format!("{:.*e}", precision - 1, value);
+    let exponent: i32 = formatted.split_once('e').unwrap().1.parse().unwrap();
+
+    let body = match exponent < -4 || exponent >= precision as i32 {
+        true => format_exponent(value, precision - 1, alternate),
+        false => {
+            let decimals = (precision as i32 - 1 - exponent) as usize;
+            let body = format!("{:.*}", decimals, value);
+            match alternate && !body.contains('.') {
+                true => body + ".",
+                false => body,
+            }
+        }
+    };
+
+    if alternate {
+        return body;
+    }
+
+    // Drop Trailing Zeros From The Fraction Only
+    let (mantissa, exponent) = match body.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa.to_string(), format!("e{}", exponent)),
+        None => (body, "".to_string()),
+    };
+
+    match mantissa.contains('.') {
+        true => {
+            mantissa
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+                + &exponent
+        }
+        false => mantissa + &exponent,
+    }
+}
+
+fn pad(
+    prefix: &[u8],
+    body: &[u8],
+    width: Option<usize>,
+    is_left: bool,
+    is_zero_padded: bool,
+) -> Vec<u8> {
+    let length = prefix.len() + body.len();
+    let fill = width.unwrap_or(0).saturating_sub(length);
+    let mut result = Vec::with_capacity(length + fill);
+
+    match (is_left, is_zero_padded) {
+        (true, _) => {
+            result.extend_from_slice(prefix);
+            result.extend_from_slice(body);
+            result.resize(length + fill, b' ');
+        }
+        (false, true) => {
+            result.extend_from_slice(prefix);
+            result.resize(prefix.len() + fill, b'0');
+            result.extend_from_slice(body);
+        }
+        (false, false) => {
+            result.resize(fill, b' ');
+            result.extend_from_slice(prefix);
+            result.extend_from_slice(body);
+        }
+    }
+
+    result
+}
+
+/// Parses decimal, `0x` hex, `0` octal and `'c` character constants as far as they go, as
+/// strtoimax does
+fn parse_integer(argument: &str) -> Parsed<i128> {
+    let value = argument.trim_start();
+
+    if let Some(code) = parse_character_constant(value) {
+        return (code as i128, None);
+    }
+
+    if value.is_empty() {
+        return (0, None);
+    }
+
+    let (is_negative, digits) = match value.as_bytes()[0] {
+        b'-' => (true, &value[1..]),
+        b'+' => (false, &value[1..]),
+        _ => (false, value),
+    };
+
+    // A Prefix Without Digits After It Leaves Just Its Zero Read
+    let (radix, digits, has_zero) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (16, hex, true)
+    } else if let Some(octal) = digits.strip_prefix('0') {
+        (8, octal, true)
+    } else {
+        (10, digits, false)
+    };
+
+    let length = digits
+        .find(|character: char| !character.is_digit(radix))
+        .unwrap_or(digits.len());
+    let rest = match (length, has_zero) {
+        (0, true) if radix == 16 => &value[value.len() - digits.len() - 1..],
+        _ => &digits[length..],
+    };
+
+    if length == 0 && !has_zero {
+        return (0, Some(CustomError::InvalidNumber(argument.to_string())));
+    }
+
+    // Saturating Keeps Huge Values Past Every Range They Are Checked Against
+    let magnitude = digits[..length].chars().fold(0i128, |total, character| {
+        let digit = character.to_digit(radix).unwrap_or(0) as i128;
+        total.saturating_mul(radix as i128).saturating_add(digit)
+    });
+    let value = if is_negative { -magnitude } else { magnitude };
+
+    match rest.is_empty() {
+        true => (value, None),
+        false => (value, Some(not_converted(argument))),
+    }
+}
+
+/// Parses the longest prefix that is a number as strtold does, including `0x` hex integers
+fn parse_float(argument: &str) -> Parsed<f64> {
+    let value = argument.trim_start();
+
+    if let Some(code) = parse_character_constant(value) {
+        return (code as f64, None);
+    }
+
+    if value.is_empty() {
+        return (0.0, None);
+    }
+
+    let is_hex = value
+        .trim_start_matches(['-', '+'])
+        .get(..2)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("0x"));
+
+    let prefix = (1..=value.len())
+        .rev()
+        .filter(|&length| value.is_char_boundary(length))
+        .find_map(|length| {
+            let prefix = &value[..length];
+            match is_hex {
+                true => match parse_integer(prefix) {
+                    (integer, None) => Some((integer as f64, length)),
+                    _ => None,
+                },
+                false => prefix.parse::<f64>().ok().map(|float| (float, length)),
+            }
+        });
+
+    match prefix {
+        Some((float, length)) if length == value.len() => (float, None),
+        Some((float, _)) => (float, Some(not_converted(argument))),
+        None => (0.0, Some(CustomError::InvalidNumber(argument.to_string()))),
+    }
+}
+
+fn not_converted(argument: &str) -> CustomError {
+    CustomError::NumberNotConverted(argument.to_string())
+}
+
+/// A leading quote makes the value the code of the following character
+fn parse_character_constant(value: &str) -> Option<u32> {
+    let rest = value
+        .strip_prefix('\'')
+        .or_else(|| value.strip_prefix('"'))?;
+    Some(rest.chars().next().map_or(0, u32::from))
+}
@@ -0,0 +1,86 @@
+/// Characters that never need quoting in a POSIX shell word
+const SHELL_SAFE: &[u8] = b"_@%+=:,./-";
+
+/// Writes a JSON array of strings, replacing invalid UTF-8 with U+FFFD
+pub fn json_array(fields: &[Vec<u8>]) -> Vec<u8> {
+    let mut result = vec![b'['];
+
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            result.push(b',');
+        }
+        result.extend(json_string(field));
+    }
+
+    result.push(b']');
+    result
+}
+
+/// Writes a JSON string, escaping quotes, backslashes and control characters
+pub fn json_string(field: &[u8]) -> Vec<u8> {
+    let mut result = String::with_capacity(field.len() + 2);
+    result.push('"');
+
+    for character in String::from_utf8_lossy(field).chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\x08' => result.push_str("\\b"),
+            '\x0c' => result.push_str("\\f"),
+            '\0'..='\x1f' | '\x7f' => result.push_str(&format!("\\u{:04x}", character as u32)),
+            _ => result.push(character),
+        }
+    }
+
+    result.push('"');
+    result.into_bytes()
+}
+
+/// Quotes each field as a single POSIX sh word, separated by spaces
+pub fn shell_words(fields: &[Vec<u8>]) -> Vec<u8> {
+    let words: Vec<Vec<u8>> = fields.iter().map(|field| shell_word(field)).collect();
+    words.join(&b' ')
+}
+
+/// Safe words are left bare, everything else is single quoted with `'` written as `'\''`
+fn shell_word(field: &[u8]) -> Vec<u8> {
+    let is_safe = |byte: &u8| byte.is_ascii_alphanumeric() || SHELL_SAFE.contains(byte);
+    if !field.is_empty() && field.iter().all(is_safe) {
+        return field.to_vec();
+    }
+
+    let mut result = vec![b'\''];
+    for &byte in field {
+        match byte {
+            b'\'' => result.extend_from_slice(b"'\\''"),
+            _ => result.push(byte),
+        }
+    }
+    result.push(b'\'');
+    result
+}
+
+/// Writes one RFC 4180 record, quoting fields containing commas, quotes or line breaks
+pub fn csv_row(fields: &[Vec<u8>]) -> Vec<u8> {
+    let fields: Vec<Vec<u8>> = fields.iter().map(|field| csv_field(field)).collect();
+    fields.join(&b',')
+}
+
+fn csv_field(field: &[u8]) -> Vec<u8> {
+    if !field.iter().any(|byte| b",\"\r\n".contains(byte)) {
+        return field.to_vec();
+    }
+
+    let mut result = vec![b'"'];
+    for &byte in field {
+        if byte == b'"' {
+            result.push(b'"');
+        }
+        result.push(byte);
+    }
+    result.push(b'"');
+    result
+}
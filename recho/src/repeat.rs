@@ -0,0 +1,42 @@
+use std::io::{ErrorKind, Write};
+
+use crate::CustomError;
+
+const BUFFER_SIZE: usize = 128 * 1024;
+
+/// Writes `line` forever, or `count` times, from a buffer pre-filled with whole copies of it
+pub fn write_repeated<W: Write>(
+    line: &[u8],
+    count: Option<u64>,
+    writer: &mut W,
+) -> Result<(), CustomError> {
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let copies = (BUFFER_SIZE / line.len()).max(1);
+    let buffer = line.repeat(copies);
+
+    let result = match count {
+        None => loop {
+            if let Err(error) = writer.write_all(&buffer) {
+                break Err(error);
+            }
+        },
+        Some(count) => {
+            let full_buffers = count / copies as u64;
+            let remainder = (count % copies as u64) as usize;
+
+            (0..full_buffers)
+                .try_for_each(|_| writer.write_all(&buffer))
+                .and_then(|_| writer.write_all(&buffer[..remainder * line.len()]))
+                .and_then(|_| writer.flush())
+        }
+    };
+
+    // The Reader Closing The Pipe Is The Normal Way To Stop
+    match result {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => Err(CustomError::WriteFailed),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,134 @@
+use std::ffi::OsString;
+
+use crate::CustomError;
+
+const RESET: &[u8] = b"\x1b[0m";
+
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    codes: Vec<String>,
+}
+
+impl Style {
+    /// Wraps `value` in the SGR escape sequence for this style and a reset. The reset comes before
+    /// any trailing line breaks, so the style never carries over onto the next line.
+    pub fn paint(&self, value: Vec<u8>) -> Vec<u8> {
+        if self.codes.is_empty() {
+            return value;
+        }
+
+        let end = value
+            .iter()
+            .rposition(|byte| !matches!(byte, b'\n' | b'\r'))
+            .map_or(0, |index| index + 1);
+
+        let mut result = format!("\x1b[{}m", self.codes.join(";")).into_bytes();
+        result.extend_from_slice(&value[..end]);
+        result.extend_from_slice(RESET);
+        result.extend_from_slice(&value[end..]);
+        result
+    }
+}
+
+/// Splits the `--color` values into the color mode and the foreground color, then adds the text styles
+pub fn parse_style(
+    colors: &[String],
+    styles: &[String],
+    bold: bool,
+    italic: bool,
+    underline: bool,
+) -> Result<(ColorMode, Style), CustomError> {
+    let mut mode = ColorMode::Auto;
+    let mut style = Style::default();
+
+    for color in colors {
+        match color.as_str() {
+            "auto" => mode = ColorMode::Auto,
+            "always" => mode = ColorMode::Always,
+            "never" => mode = ColorMode::Never,
+            _ => style.codes.push(parse_color(color)?),
+        }
+    }
+
+    let styles = styles
+        .iter()
+        .flat_map(|style| style.split(','))
+        .chain(bold.then_some("bold"))
+        .chain(italic.then_some("italic"))
+        .chain(underline.then_some("underline"));
+
+    for name in styles {
+        let code = match name.trim() {
+            "bold" => "1",
+            "dim" => "2",
+            "italic" => "3",
+            "underline" => "4",
+            "blink" => "5",
+            "reverse" => "7",
+            "strikethrough" => "9",
+            _ => return Err(CustomError::InvalidStyle(name.to_string())),
+        };
+        style.codes.push(code.to_string());
+    }
+
+    Ok((mode, style))
+}
+
+/// Parses one of the 16 color names, a 256 color index or a `#RRGGBB` truecolor value
+fn parse_color(color: &str) -> Result<String, CustomError> {
+    let name = color.to_ascii_lowercase();
+
+    if let Some(index) = COLORS.iter().position(|&known| known == name) {
+        return Ok((30 + index).to_string());
+    }
+
+    let bright = name
+        .strip_prefix("bright-")
+        .or_else(|| name.strip_prefix("bright"));
+    if let Some(index) = bright.and_then(|name| COLORS.iter().position(|&known| known == name)) {
+        return Ok((90 + index).to_string());
+    }
+
+    if let Ok(index) = name.parse::<u8>() {
+        return Ok(format!("38;5;{}", index));
+    }
+
+    let hex = name.strip_prefix('#').filter(|hex| hex.len() == 6);
+    match hex.and_then(|hex| u32::from_str_radix(hex, 16).ok()) {
+        Some(rgb) => Ok(format!(
+            "38;2;{};{};{}",
+            rgb >> 16,
+            (rgb >> 8) & 0xff,
+            rgb & 0xff
+        )),
+        None => Err(CustomError::InvalidColor(color.to_string())),
+    }
+}
+
+/// Explicit modes win, then FORCE_COLOR, then NO_COLOR, then whether stdout is a terminal
+pub fn is_style_enabled(
+    mode: ColorMode,
+    is_tty: bool,
+    no_color: Option<OsString>,
+    force_color: Option<OsString>,
+) -> bool {
+    let is_set = |value: &Option<OsString>| value.as_ref().is_some_and(|value| !value.is_empty());
+
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto if is_set(&force_color) => force_color.unwrap() != "0",
+        ColorMode::Auto => is_tty && !is_set(&no_color),
+    }
+}
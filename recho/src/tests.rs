@@ -1,22 +1,422 @@
 use super::*;
+use std::ffi::OsString;
+use style::ColorMode;
 use test_case::test_case;
 
-#[test_case(vec!["test".to_string()], "test".to_string(), None)]
-#[test_case(vec!["test".to_string(), "test2".to_string()], "test\ntest2".to_string(), None)]
-#[test_case(vec![], "".to_string(), Some(CustomError::EmptyValues))]
+#[test_case(vec!["test".to_string()], true, false, b"test\n", None; "one value")]
+#[test_case(vec!["test".to_string(), "test2".to_string()], true, false, b"test test2\n", None; "values joined with spaces")]
+#[test_case(vec![], true, false, b"\n", None; "no values")]
+#[test_case(vec!["test".to_string()], false, false, b"test", None; "no trailing newline")]
+#[test_case(vec![], false, false, b"", None; "no values + no trailing newline")]
+#[test_case(vec!["a\\tb".to_string()], true, false, b"a\\tb\n", None; "escapes kept without -e")]
+#[test_case(vec!["a\\tb".to_string(), "c\\nd".to_string()], true, true, b"a\tb c\nd\n", None; "escapes across values")]
+#[test_case(vec!["a\\cb".to_string(), "c".to_string()], true, true, b"a", None; "stop output drops newline")]
 fn test_process_echo(
     values: Vec<String>,
-    expected_result: String,
+    trailing_newline: bool,
+    escapes: bool,
+    expected_result: &[u8],
     expected_error: Option<CustomError>,
 ) {
     match expected_error {
         None => assert_eq!(
             expected_result,
-            process_echo(values).unwrap()
+            process_echo(
+                values,
+                trailing_newline,
+                escapes,
+                Expansion::Disabled,
+                Output::Echo,
+                &Style::default()
+            ).unwrap()
         ),
         Some(expected_error) => assert_eq!(
             Err(expected_error),
-            process_echo(values)
+            process_echo(
+                values,
+                trailing_newline,
+                escapes,
+                Expansion::Disabled,
+                Output::Echo,
+                &Style::default()
+            )
         ),
     }
 }
+
+#[test_case("\\a", b"\x07", false; "alert")]
+#[test_case("\\b", b"\x08", false; "backspace")]
+#[test_case("a\\cb", b"a", true; "stop output")]
+#[test_case("\\e", b"\x1b", false; "escape")]
+#[test_case("\\f", b"\x0c", false; "form feed")]
+#[test_case("\\n", b"\n", false; "newline")]
+#[test_case("\\r", b"\r", false; "carriage return")]
+#[test_case("\\t", b"\t", false; "tab")]
+#[test_case("\\v", b"\x0b", false; "vertical tab")]
+#[test_case("\\\\", b"\\", false; "backslash")]
+#[test_case("\\0", b"\x00", false; "octal without digits")]
+#[test_case("\\0101", b"A", false; "octal three digits")]
+#[test_case("\\01012", b"A2", false; "octal stops after three digits")]
+#[test_case("\\0377", b"\xff", false; "octal high byte")]
+#[test_case("\\08", b"\x008", false; "octal stops at non octal digit")]
+#[test_case("\\x41", b"A", false; "hex two digits")]
+#[test_case("\\x4", b"\x04", false; "hex one digit")]
+#[test_case("\\x414", b"A4", false; "hex stops after two digits")]
+#[test_case("\\xc3\\xa9", "é".as_bytes(), false; "hex utf-8 bytes")]
+#[test_case("\\xg", b"\\xg", false; "hex without digits")]
+#[test_case("\\u00e9", "é".as_bytes(), false; "unicode four digits")]
+#[test_case("\\u263a!", "☺!".as_bytes(), false; "unicode followed by text")]
+#[test_case("\\U0001F600", "😀".as_bytes(), false; "unicode eight digits")]
+#[test_case("\\ud800", b"\\ud800", false; "unicode surrogate kept")]
+#[test_case("\\uzz", b"\\uzz", false; "unicode without digits")]
+#[test_case("\\q", b"\\q", false; "unknown escape")]
+#[test_case("end\\", b"end\\", false; "trailing backslash")]
+#[test_case("plain", b"plain", false; "no escapes")]
+fn test_process_escapes(value: &str, expected_result: &[u8], expected_stopped: bool) {
+    assert_eq!(
+        (expected_result.to_vec(), expected_stopped),
+        process_escapes(value, true)
+    );
+}
+
+#[test_case("$NAME", false, "rust", None; "plain variable")]
+#[test_case("${NAME}y", false, "rusty", None; "braced variable")]
+#[test_case("$NAME-$NAME", false, "rust-rust", None; "variable ends at non name character")]
+#[test_case("${MISSING:-fallback}", false, "fallback", None; "default for unset variable")]
+#[test_case("${EMPTY:-fallback}", false, "fallback", None; "default for empty variable")]
+#[test_case("${NAME:-fallback}", false, "rust", None; "default unused")]
+#[test_case("${EMPTY}!", true, "!", None; "empty variable is set in strict mode")]
+#[test_case("a${MISSING}b", false, "ab", None; "unset variable expands to nothing")]
+#[test_case("a$MISSING b", true, "", Some(CustomError::UnsetVariable("MISSING".to_string())); "unset variable in strict mode")]
+#[test_case("${MISSING:-ok}", true, "ok", None; "default in strict mode")]
+#[test_case("cost: $5 and $", true, "cost: $5 and $", None; "dollar without name kept")]
+#[test_case("${1BAD} ${unclosed", true, "${1BAD} ${unclosed", None; "invalid braces kept")]
+#[test_case("é$NAME☺", false, "érust☺", None; "non ascii text")]
+fn test_expand_variables(
+    value: &str,
+    is_strict: bool,
+    expected_result: &str,
+    expected_error: Option<CustomError>,
+) {
+    let variables: std::collections::HashMap<&str, &str> =
+        [("NAME", "rust"), ("EMPTY", "")].into_iter().collect();
+    let lookup = |name: &str| variables.get(name).map(|value| value.to_string());
+
+    match expected_error {
+        None => assert_eq!(
+            expected_result,
+            expand_variables(value, is_strict, lookup).unwrap()
+        ),
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            expand_variables(value, is_strict, lookup)
+        ),
+    }
+}
+
+#[test_case(vec!["${RECHO_TEST_VALUE}".to_string()], false, Expansion::Enabled, b"a\\tb\n", None; "expanded value is not escaped without -e")]
+#[test_case(vec!["${RECHO_TEST_VALUE}".to_string()], true, Expansion::Enabled, b"a\tb\n", None; "escapes applied after expansion")]
+#[test_case(vec!["$RECHO_TEST_VALUE".to_string()], false, Expansion::Disabled, b"$RECHO_TEST_VALUE\n", None; "expansion disabled")]
+#[test_case(vec!["$RECHO_TEST_UNSET".to_string()], false, Expansion::Strict, b"", Some(CustomError::UnsetVariable("RECHO_TEST_UNSET".to_string())); "strict unset")]
+fn test_process_echo_expansion(
+    values: Vec<String>,
+    escapes: bool,
+    expansion: Expansion,
+    expected_result: &[u8],
+    expected_error: Option<CustomError>,
+) {
+    std::env::set_var("RECHO_TEST_VALUE", "a\\tb");
+    std::env::remove_var("RECHO_TEST_UNSET");
+
+    match expected_error {
+        None => assert_eq!(
+            expected_result,
+            process_echo(
+                values,
+                true,
+                escapes,
+                expansion,
+                Output::Echo,
+                &Style::default()
+            ).unwrap()
+        ),
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            process_echo(
+                values,
+                true,
+                escapes,
+                expansion,
+                Output::Echo,
+                &Style::default()
+            )
+        ),
+    }
+}
+
+#[test_case(vec!["red"], vec![], false, b"\x1b[31m", None; "basic color")]
+#[test_case(vec!["bright-blue"], vec![], false, b"\x1b[94m", None; "bright color")]
+#[test_case(vec!["BrightWhite"], vec![], false, b"\x1b[97m", None; "bright color without dash")]
+#[test_case(vec!["208"], vec![], false, b"\x1b[38;5;208m", None; "256 color index")]
+#[test_case(vec!["#FF8000"], vec![], false, b"\x1b[38;2;255;128;0m", None; "truecolor hex")]
+#[test_case(vec!["always", "green"], vec!["italic,underline"], true, b"\x1b[32;3;4;1m", None; "mode color and styles")]
+#[test_case(vec![], vec!["bold"], false, b"\x1b[1m", None; "style without color")]
+#[test_case(vec!["256"], vec![], false, b"", Some(CustomError::InvalidColor("256".to_string())); "color index out of range")]
+#[test_case(vec!["#FFF"], vec![], false, b"", Some(CustomError::InvalidColor("#FFF".to_string())); "short hex")]
+#[test_case(vec!["purple"], vec![], false, b"", Some(CustomError::InvalidColor("purple".to_string())); "unknown color")]
+#[test_case(vec![], vec!["bold,wavy"], false, b"", Some(CustomError::InvalidStyle("wavy".to_string())); "unknown style")]
+fn test_parse_style(
+    colors: Vec<&str>,
+    styles: Vec<&str>,
+    bold: bool,
+    expected_prefix: &[u8],
+    expected_error: Option<CustomError>,
+) {
+    let colors: Vec<String> = colors.into_iter().map(String::from).collect();
+    let styles: Vec<String> = styles.into_iter().map(String::from).collect();
+
+    match expected_error {
+        None => {
+            let (_, style) = style::parse_style(&colors, &styles, bold, false, false).unwrap();
+            let expected_result = [expected_prefix, b"x\x1b[0m"].concat();
+            assert_eq!(expected_result, style.paint(b"x".to_vec()));
+        }
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            style::parse_style(&colors, &styles, bold, false, false)
+        ),
+    }
+}
+
+#[test_case(b"x", b"\x1b[31mx\x1b[0m"; "no newline")]
+#[test_case(b"x\n", b"\x1b[31mx\x1b[0m\n"; "reset before newline")]
+#[test_case(b"a\nb\r\n\n", b"\x1b[31ma\nb\x1b[0m\r\n\n"; "reset before every trailing line break")]
+#[test_case(b"\n", b"\x1b[31m\x1b[0m\n"; "only a newline")]
+fn test_paint(value: &[u8], expected_result: &[u8]) {
+    let (_, style) = style::parse_style(&["red".to_string()], &[], false, false, false).unwrap();
+
+    assert_eq!(expected_result, style.paint(value.to_vec()));
+}
+
+#[test_case(ColorMode::Auto, true, None, None, true; "auto on terminal")]
+#[test_case(ColorMode::Auto, false, None, None, false; "auto when piped")]
+#[test_case(ColorMode::Auto, true, Some("1"), None, false; "no color on terminal")]
+#[test_case(ColorMode::Auto, true, Some(""), None, true; "empty no color ignored")]
+#[test_case(ColorMode::Auto, false, None, Some("1"), true; "force color when piped")]
+#[test_case(ColorMode::Auto, true, Some("1"), Some("1"), true; "force color beats no color")]
+#[test_case(ColorMode::Auto, true, None, Some("0"), false; "force color zero disables")]
+#[test_case(ColorMode::Always, false, Some("1"), None, true; "always beats no color")]
+#[test_case(ColorMode::Never, true, None, Some("1"), false; "never beats force color")]
+fn test_is_style_enabled(
+    mode: ColorMode,
+    is_tty: bool,
+    no_color: Option<&str>,
+    force_color: Option<&str>,
+    expected_result: bool,
+) {
+    assert_eq!(
+        expected_result,
+        style::is_style_enabled(
+            mode,
+            is_tty,
+            no_color.map(OsString::from),
+            force_color.map(OsString::from)
+        )
+    );
+}
+
+#[test_case(vec!["FAILED".to_string()], true, b"\x1b[31;1mFAILED\x1b[0m\n"; "newline after reset")]
+#[test_case(vec!["a\\cb".to_string()], true, b"\x1b[31;1ma\x1b[0m"; "reset kept when output stops")]
+#[test_case(vec!["x\\n".to_string()], true, b"\x1b[31;1mx\x1b[0m\n\n"; "reset before escaped newline")]
+fn test_process_echo_style(values: Vec<String>, escapes: bool, expected_result: &[u8]) {
+    let colors = vec!["red".to_string()];
+    let (_, style) = style::parse_style(&colors, &[], true, false, false).unwrap();
+
+    assert_eq!(
+        expected_result,
+        process_echo(
+            values,
+            true,
+            escapes,
+            Expansion::Disabled,
+            Output::Echo,
+            &style
+        ).unwrap()
+    );
+}
+
+#[test_case(vec!["a", "b c"], Output::JsonArray, false, b"[\"a\",\"b c\"]\n"; "json array")]
+#[test_case(vec![], Output::JsonArray, false, b"[]\n"; "json empty array")]
+#[test_case(vec!["say \"hi\"", "back\\slash"], Output::JsonArray, false, b"[\"say \\\"hi\\\"\",\"back\\\\slash\"]\n"; "json embedded quotes")]
+#[test_case(vec!["a\\nb\\tc\\x01"], Output::JsonArray, true, b"[\"a\\nb\\tc\\u0001\"]\n"; "json control characters")]
+#[test_case(vec!["héllo", "☺"], Output::JsonArray, false, "[\"héllo\",\"☺\"]\n".as_bytes(); "json non ascii")]
+#[test_case(vec!["\\xff"], Output::JsonArray, true, "[\"\u{fffd}\"]\n".as_bytes(); "json invalid utf-8 replaced")]
+#[test_case(vec!["a", "\"b\"", "c\\nd"], Output::JsonString, true, b"\"a \\\"b\\\" c\\nd\"\n"; "json joined string")]
+#[test_case(vec!["plain", "a-b_c.d/e=f"], Output::ShellQuote, false, b"plain a-b_c.d/e=f\n"; "shell safe words bare")]
+#[test_case(vec!["", "a b", "$HOME"], Output::ShellQuote, false, b"'' 'a b' '$HOME'\n"; "shell empty space and dollar")]
+#[test_case(vec!["it's", "\"quoted\""], Output::ShellQuote, false, b"'it'\\''s' '\"quoted\"'\n"; "shell embedded quotes")]
+#[test_case(vec!["a\\nb"], Output::ShellQuote, true, b"'a\nb'\n"; "shell embedded newline")]
+#[test_case(vec!["héllo"], Output::ShellQuote, false, "'héllo'\n".as_bytes(); "shell non ascii")]
+#[test_case(vec!["a", "b c", ""], Output::Csv, false, b"a,b c,\r\n"; "csv plain fields")]
+#[test_case(vec!["a,b", "say \"hi\""], Output::Csv, false, b"\"a,b\",\"say \"\"hi\"\"\"\r\n"; "csv commas and quotes")]
+#[test_case(vec!["line\\r\\nbreak"], Output::Csv, true, b"\"line\r\nbreak\"\r\n"; "csv embedded line break")]
+#[test_case(vec!["héllo", "☺"], Output::Csv, false, "héllo,☺\r\n".as_bytes(); "csv non ascii")]
+#[test_case(vec!["a", "b\\cc", "d"], Output::Csv, true, b"a,b"; "stop output keeps earlier fields")]
+fn test_process_echo_output(
+    values: Vec<&str>,
+    output: Output,
+    escapes: bool,
+    expected_result: &[u8],
+) {
+    let values: Vec<String> = values.into_iter().map(String::from).collect();
+
+    assert_eq!(
+        expected_result,
+        process_echo(
+            values,
+            true,
+            escapes,
+            Expansion::Disabled,
+            output,
+            &Style::default()
+        )
+        .unwrap()
+    );
+}
+
+// Expected outputs match GNU coreutils printf
+#[test_case("%-10s|%5.2f\\n", vec!["name", "3.14159"], b"name      | 3.14\n", None; "padded string and float")]
+#[test_case("%s %s\\n", vec!["a", "b", "c"], b"a b\nc \n", None; "format reused for surplus arguments")]
+#[test_case("%d %i %u\\n", vec!["42", "-7", "-1"], b"42 -7 18446744073709551615\n", None; "signed and unsigned")]
+#[test_case("%o %x %X %#o %#x %#X\\n", vec!["8", "255", "255", "8", "255", "0"], b"10 ff FF 010 0xff 0\n", None; "octal and hex")]
+#[test_case("%5d|%-5d|%05d|%+d|% d\\n", vec!["42", "42", "-42", "42", "42"], b"   42|42   |-0042|+42| 42\n", None; "integer flags")]
+#[test_case("%.3d|%8.3d|%-8.3d|%08.3d\\n", vec!["7", "7", "-7", "7"], b"007|     007|-007    |     007\n", None; "integer precision")]
+#[test_case("%.0d|%.0x|\\n", vec!["0", "0"], b"||\n", None; "zero precision zero value")]
+#[test_case("%f %.2f %.0f %#.0f\\n", vec!["3.14159", "2.675", "2.5", "3"], b"3.141590 2.67 2 3.\n", None; "fixed point")]
+#[test_case("%e %E %.2e %e\\n", vec!["12345.678", "0.000123", "1", "0"], b"1.234568e+04 1.230000E-04 1.00e+00 0.000000e+00\n", None; "exponent")]
+#[test_case("%g %g %g %g %G\\n", vec!["0.0001", "1e-5", "123456", "1234567", "1e-10"], b"0.0001 1e-05 123456 1.23457e+06 1E-10\n", None; "general")]
+#[test_case("%g %#g %.3g %.10g\\n", vec!["100", "1.5", "3.14159", "3.14159"], b"100 1.50000 3.14 3.14159\n", None; "general precision")]
+#[test_case("%*d|%-*d|%.*f\\n", vec!["5", "42", "5", "42", "2", "3.14159"], b"   42|42   |3.14\n", None; "star width and precision")]
+#[test_case("%*d|\\n", vec!["-4", "1"], b"1   |\n", None; "negative star width left aligns")]
+#[test_case("%c%c%c\\n", vec!["abc", "é", ""], b"a\xc3\0\n", None; "characters")]
+#[test_case("%s|%d|%f\\n", vec![], b"|0|0.000000\n", None; "missing arguments")]
+#[test_case("%b\\n", vec!["a\\tb\\0101\\n"], b"a\tbA\n\n", None; "b conversion escapes")]
+#[test_case("[%b]\\n", vec!["x\\cy", "z"], b"[x", None; "b conversion stop")]
+#[test_case("%%|\\n", vec![], b"%|\n", None; "percent")]
+#[test_case("%d %d %d %x\\n", vec!["0x1F", "010", "'A", "\"a"], b"31 8 65 61\n", None; "numeric argument forms")]
+#[test_case("%5s|%-5s|%.1s|\\n", vec!["ab", "ab", "xyz"], b"   ab|ab   |x|\n", None; "string width and precision")]
+#[test_case("%f %f %F\\n", vec!["inf", "-inf", "nan"], b"inf -inf NAN\n", None; "infinity and nan")]
+#[test_case("%05.1f|%-8.3e|%+.2f\\n", vec!["-3.14159", "31415.9", "2"], b"-03.1|3.142e+04|+2.00\n", None; "float flags")]
+#[test_case("\\101\\x42\\t%s\\n", vec!["x"], b"AB\tx\n", None; "format escapes")]
+#[test_case("no conversions\\n", vec!["a", "b"], b"no conversions\n", None; "surplus arguments without conversions")]
+#[test_case("%d\\n", vec!["-9223372036854775808"], b"-9223372036854775808\n", None; "smallest integer")]
+#[test_case("%u\\n", vec!["18446744073709551615"], b"18446744073709551615\n", None; "largest unsigned")]
+#[test_case("%5%|\\n", vec![], b"", Some(CustomError::InvalidFormat("%5%".to_string())); "invalid conversion")]
+#[test_case("%z\\n", vec![], b"", Some(CustomError::InvalidFormat("%z".to_string())); "unknown conversion")]
+#[test_case("%.*d|\\n", vec!["-99999999999", "1"], b"1|\n", None; "huge negative star precision ignored")]
+#[test_case("%99999999999999d|\\n", vec!["1"], b"", Some(CustomError::InvalidFieldWidth("99999999999999".to_string())); "huge width")]
+#[test_case("%*d|\\n", vec!["99999999999999999999", "1"], b"", Some(CustomError::InvalidFieldWidth("99999999999999999999".to_string())); "huge star width")]
+#[test_case("%*d|\\n", vec!["2147483648", "1"], b"", Some(CustomError::InvalidFieldWidth("2147483648".to_string())); "star width past int")]
+#[test_case("%*d|\\n", vec!["-9223372036854775808", "1"], b"", Some(CustomError::InvalidFieldWidth("-9223372036854775808".to_string())); "smallest star width")]
+#[test_case("%.99999999999999999999d|\\n", vec!["1"], b"", Some(CustomError::InvalidPrecision("99999999999999999999".to_string())); "huge precision")]
+#[test_case("%.*d|\\n", vec!["2147483648", "1"], b"", Some(CustomError::InvalidPrecision("2147483648".to_string())); "star precision past int")]
+#[test_case("trailing %", vec![], b"", Some(CustomError::InvalidFormat("%".to_string())); "missing conversion")]
+fn test_process_printf(
+    format: &str,
+    arguments: Vec<&str>,
+    expected_result: &[u8],
+    expected_error: Option<CustomError>,
+) {
+    let arguments: Vec<String> = arguments.into_iter().map(String::from).collect();
+
+    match expected_error {
+        None => assert_eq!(
+            expected_result,
+            printf::process_printf(format, &arguments, &mut vec![]).unwrap()
+        ),
+        Some(expected_error) => assert_eq!(
+            Err(expected_error),
+            printf::process_printf(format, &arguments, &mut vec![])
+        ),
+    }
+}
+
+// Arguments that are not numbers are reported and printed as far as they go, as coreutils does
+#[test_case("%d\\n", vec!["abc"], b"0\n", "Error: invalid number provided: abc\n"; "invalid number")]
+#[test_case("%d\\n", vec!["12abc"], b"12\n", "Error: number not completely converted: 12abc\n"; "partial number")]
+#[test_case("%x %o\\n", vec!["0xg", "09"], b"0 0\n", "Error: number not completely converted: 0xg\nError: number not completely converted: 09\n"; "prefix without digits")]
+#[test_case("%f|%f\\n", vec!["1.2.3", "0x1Fz"], b"1.200000|31.000000\n", "Error: number not completely converted: 1.2.3\nError: number not completely converted: 0x1Fz\n"; "partial float")]
+#[test_case("%d\\n", vec!["9223372036854775808"], b"9223372036854775807\n", "Error: number out of range provided: 9223372036854775808\n"; "integer out of range")]
+#[test_case("%u\\n", vec!["18446744073709551616"], b"18446744073709551615\n", "Error: number out of range provided: 18446744073709551616\n"; "unsigned out of range")]
+#[test_case("%d %s|", vec!["a", "x", "2", "y", "b", "z"], b"0 x|2 y|0 z|", "Error: invalid number provided: a\nError: invalid number provided: b\n"; "later arguments still printed")]
+#[test_case("%*d|\\n", vec!["x", "5"], b"5|\n", "Error: invalid number provided: x\n"; "invalid star width")]
+fn test_process_printf_invalid_numbers(
+    format: &str,
+    arguments: Vec<&str>,
+    expected_result: &[u8],
+    expected_errors: &str,
+) {
+    let arguments: Vec<String> = arguments.into_iter().map(String::from).collect();
+    let mut errors = vec![];
+
+    assert_eq!(
+        expected_result,
+        printf::process_printf(format, &arguments, &mut errors).unwrap()
+    );
+    assert_eq!(expected_errors, String::from_utf8(errors).unwrap());
+}
+
+#[test_case(b"y\n", Some(3), b"y\ny\ny\n"; "count")]
+#[test_case(b"y\n", Some(0), b""; "zero count")]
+#[test_case(b"", None, b""; "empty line stops immediately")]
+fn test_write_repeated(line: &[u8], count: Option<u64>, expected_result: &[u8]) {
+    let mut writer = vec![];
+    repeat::write_repeated(line, count, &mut writer).unwrap();
+
+    assert_eq!(expected_result, writer);
+}
+
+#[test]
+fn test_write_repeated_beyond_buffer() {
+    let line = b"hello world\n";
+    let mut writer = vec![];
+    repeat::write_repeated(line, Some(100_000), &mut writer).unwrap();
+
+    assert_eq!(line.repeat(100_000), writer);
+}
+
+struct ClosedPipe {
+    written: usize,
+    kind: io::ErrorKind,
+}
+
+impl Write for ClosedPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.written {
+            0 => Err(io::Error::from(self.kind)),
+            _ => {
+                let length = buf.len().min(self.written);
+                self.written -= length;
+                Ok(length)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test_case(io::ErrorKind::BrokenPipe, Ok(()); "broken pipe is quiet")]
+#[test_case(io::ErrorKind::PermissionDenied, Err(CustomError::WriteFailed); "other errors reported")]
+fn test_write_repeated_closed(kind: io::ErrorKind, expected_result: Result<(), CustomError>) {
+    let mut writer = ClosedPipe {
+        written: 1_000_000,
+        kind,
+    };
+
+    assert_eq!(
+        expected_result,
+        repeat::write_repeated(b"y\n", None, &mut writer)
+    );
+}